@@ -0,0 +1,54 @@
+// Benchmarks, run with `cargo bench`, or `cargo bench -- <name>` for just the ones whose names
+// contain <name>. They have their own little harness rather than the built-in one, as that's
+// only on nightly.
+
+extern crate last_layer_algs;
+
+use std::hint::black_box;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use last_layer_algs::{Algorithm, AlgorithmIterator, case_key};
+
+// Times `f`, first finding how many runs take a tenth of a second or so, then taking the fastest
+// of ten lots of that many.
+fn bench<T, F: FnMut() -> T>(name: &str, filters: &[String], mut f: F) {
+    if !filters.is_empty() && !filters.iter().any(|filter| name.contains(filter.as_str())) {
+        return;
+    }
+    let mut time = |iterations: u32| {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            black_box(f());
+        }
+        start.elapsed()
+    };
+    let mut iterations = 1;
+    while time(iterations) < Duration::from_millis(100) {
+        iterations *= 2;
+    }
+    let fastest = (0..10).map(|_| time(iterations)).min().unwrap();
+    println!("{:<32} {:>14} ns/iter", name, (fastest / iterations).as_nanos());
+}
+
+fn main() {
+    let filters: Vec<String> = std::env::args().skip(1).filter(|arg| !arg.starts_with("--")).collect();
+    let sune = Algorithm::from_str("R U R' U R U2 R'").unwrap();
+    let t_perm = Algorithm::from_str("R U R' U' R' F R2 U' R' U' R U R' F'").unwrap();
+    let (sune_case, t_perm_case) = (sune.inverse().cube(), t_perm.inverse().cube());
+
+    bench("tables::generate", &filters, || last_layer_algs::generate_tables());
+    // R U R' U R U2 R' four times over, as the moves are numbered in the tables
+    let moves: Vec<usize> = [12, 0, 14, 0, 12, 1, 14].iter().cycle().take(28).cloned().collect();
+    bench("corner_orientation::twist", &filters, || last_layer_algs::twist_corners(black_box(&moves)));
+    bench("corner_orientation::by_table", &filters, || last_layer_algs::twist_corners_by_table(black_box(&moves)));
+
+    bench("cubestate::apply", &filters, || black_box(sune_case).apply(&t_perm_case));
+    bench("algorithm::parse", &filters, || Algorithm::from_str("R U R' U' R' F R2 U' R' U' R U R' F'").unwrap());
+    bench("algorithm::cube", &filters, || black_box(&t_perm).cube());
+    bench("cases::case_key", &filters, || case_key(black_box(&t_perm)));
+
+    bench("iterator::first_4", &filters, || AlgorithmIterator::new().take(4).last());
+    bench("iterator::10_after_sune", &filters, || {
+        AlgorithmIterator::from_starting_algorithm("R U R' U R U2 R'").unwrap().take(10).last()
+    });
+}
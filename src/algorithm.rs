@@ -1,5 +1,6 @@
 use ::generator::Generator;
 use ::generator::Face;
+use ::generator::Modifier;
 use ::f2l_cubestate::F2LCubeState;
 use ::cubestate::CubeState;
 use ::std::str::FromStr;
@@ -26,6 +27,132 @@ impl FromStr for Algorithm {
     }
 }
 
+// Reads `n` and `'` after a move, giving how many quarter turns clockwise it comes to, e.g. 2
+// for `2'` and 3 for `'`.
+fn parse_turns(chars: &[char], pos: &mut usize) -> usize {
+    let mut count = 0;
+    let mut any_digits = false;
+    while *pos < chars.len() && chars[*pos].is_digit(10) {
+        count = (count * 10 + chars[*pos].to_digit(10).unwrap() as usize) % 4;
+        any_digits = true;
+        *pos += 1;
+    }
+    if !any_digits {
+        count = 1;
+    }
+    if *pos < chars.len() && chars[*pos] == '\'' {
+        *pos += 1;
+        count = (4 - count) % 4;
+    }
+    count
+}
+
+// The most times a bracketed group can be repeated, and the most moves an alg can come to once
+// they have been, so that something like `(R U)100000000` is an error rather than using up all
+// the memory there is.
+const MAX_REPEATS: usize = 100;
+const MAX_EXTENDED_LENGTH: usize = 1000;
+
+fn too_long() -> LLAError {
+    LLAError::InvalidAlgorithm(format!("Algs can be at most {} moves long", MAX_EXTENDED_LENGTH))
+}
+
+fn closing_bracket(c: char) -> Option<char> {
+    match c {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        _ => None,
+    }
+}
+
+// The moves up to `closing`, or the end if there's nothing to close. `rotation` is how many y
+// turns the cube's been given so far.
+fn parse_group(chars: &[char], pos: &mut usize, rotation: &mut usize, closing: Option<char>) -> Result<Vec<Generator>, LLAError> {
+    let mut moves = vec![];
+    loop {
+        if moves.len() > MAX_EXTENDED_LENGTH {
+            return Err(too_long());
+        }
+        while *pos < chars.len() && chars[*pos].is_whitespace() {
+            *pos += 1;
+        }
+        if *pos == chars.len() {
+            return match closing {
+                Some(c) => Err(LLAError::InvalidAlgorithm(format!("No '{}' to close the brackets", c))),
+                None => Ok(moves),
+            };
+        }
+        let c = chars[*pos];
+        *pos += 1;
+        if Some(c) == closing {
+            return Ok(moves);
+        } else if let Some(close) = closing_bracket(c) {
+            let start = *pos;
+            let rotation_before = *rotation;
+            let group = parse_group(chars, pos, rotation, Some(close))?;
+            let repeats = parse_repeats(chars, pos)?;
+            if repeats == 0 {
+                *rotation = rotation_before;
+                continue;
+            }
+            moves.extend(group);
+            // any y turns in the group have turned the cube for the next time round, so the moves
+            // are read again rather than copied
+            for _ in 1..repeats {
+                if moves.len() > MAX_EXTENDED_LENGTH {
+                    return Err(too_long());
+                }
+                moves.extend(parse_group(chars, &mut start.clone(), rotation, Some(close))?);
+            }
+        } else if c == 'y' {
+            *rotation = (*rotation + parse_turns(chars, pos)) % 4;
+        } else if "UDFBRL".contains(c) {
+            let modifier = match parse_turns(chars, pos) {
+                0 => continue,
+                1 => "",
+                2 => "2",
+                _ => "'",
+            };
+            let mut g = Generator::from_str(format!("{}{}", c, modifier).as_str())?;
+            // the face that's now where this one was before the cube was turned
+            for _ in 0..(4 - *rotation) % 4 {
+                g = g.rotate_y();
+            }
+            moves.push(g);
+        } else if "xzMESudfbrlw".contains(c) {
+            return Err(LLAError::InvalidAlgorithm(format!("'{}' isn't a face turn or a y rotation, which are all that can be checked", c)));
+        } else {
+            return Err(LLAError::InvalidAlgorithm(format!("No move '{}'", c)));
+        }
+    }
+}
+
+// How many times to do a bracketed group, as in `(R U R' U')3` or `(R U R' U')x3`.
+fn parse_repeats(chars: &[char], pos: &mut usize) -> Result<usize, LLAError> {
+    let start = *pos;
+    if *pos < chars.len() && (chars[*pos] == 'x' || chars[*pos] == '*') {
+        *pos += 1;
+    }
+    let mut repeats = 0;
+    let mut any_digits = false;
+    while *pos < chars.len() && chars[*pos].is_digit(10) {
+        repeats = repeats * 10 + chars[*pos].to_digit(10).unwrap() as usize;
+        if repeats > MAX_REPEATS {
+            return Err(LLAError::InvalidAlgorithm(format!("Brackets can be repeated at most {} times", MAX_REPEATS)));
+        }
+        any_digits = true;
+        *pos += 1;
+    }
+    if any_digits {
+        Ok(repeats)
+    } else {
+        // an `x` on its own is a rotation, and not ours to read
+        *pos = start;
+        Ok(1)
+    }
+}
+
 impl Display for Algorithm {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         write!(f, "{}", self.moves.iter().map(|g| format!("{}", g)).collect::<Vec<String>>().join(" "))
@@ -33,10 +160,30 @@ impl Display for Algorithm {
 }
 
 impl Algorithm {
+    // Reads algs written the ways other lists of them tend to be, as well as the way `from_str`
+    // takes them. Moves needn't have spaces between them, brackets group moves and can say how
+    // many times to do them (up to `MAX_REPEATS`), as in `(R U R' U')3`, `R2'` is a half turn,
+    // `’` will do for `'`, and `y`, `y'` and `y2` turn the cube, changing which face the moves
+    // after them are of. Slices, wide moves and x and z rotations can't be done with the moves
+    // there are, so they're errors.
+    pub fn parse_extended(s: &str) -> Result<Self, Box<Error>> {
+        let chars: Vec<char> = s.chars().map(|c| match c {
+            '\u{2019}' | '\u{2032}' | '`' => '\'',
+            c => c,
+        }).collect();
+        let moves = parse_group(&chars, &mut 0, &mut 0, None)?;
+        Ok(Algorithm { moves: moves })
+    }
+
     pub fn length(&self) -> i8 {
         self.moves.len() as i8
     }
 
+    // The length counting half turns as two moves, where `length` counts them as one.
+    pub fn quarter_turn_length(&self) -> usize {
+        self.moves.iter().map(|m| if m.components().1 == Modifier::Twice { 2 } else { 1 }).sum()
+    }
+
     pub fn first_non_ud_move(&self) -> Option<&Generator> {
         self.moves.iter().find(|m| !m.is_u_move() && !m.is_d_move())
     }
@@ -53,12 +200,32 @@ impl Algorithm {
         result
     }
 
+    fn f2l_state(&self) -> F2LCubeState {
+        self.moves.iter().fold(F2LCubeState::new(), |cube, &m| cube.apply(m))
+    }
+
+    // Whether the alg leaves everything but the last layer as it was.
+    pub fn keeps_f2l(&self) -> bool {
+        self.f2l_state().f2l_solved()
+    }
+
+    // Whether the alg is a last layer alg: it keeps the F2L, and does more to the last layer than
+    // turn it.
+    pub fn is_ll(&self) -> bool {
+        self.f2l_state().is_ll()
+    }
+
     pub fn inverse(&self) -> Self {
         let mut moves: Vec<Generator> = self.moves.iter().map(|g| g.inverse()).collect();
         moves.reverse();
         Algorithm { moves: moves }
     }
 
+    // The alg that does the same thing in a mirror, held up to the cube's R or L face.
+    pub fn mirror(&self) -> Self {
+        Algorithm { moves: self.moves.iter().map(|g| g.mirror()).collect() }
+    }
+
     pub fn cube(&self) -> CubeState {
         let mut curr_cube = CubeState::solved();
         for m in &self.moves {
@@ -118,6 +285,7 @@ impl Algorithm {
 fn gives_alg_length() {
     let alg = Algorithm::from_str("R U R' U'").unwrap();
     assert_eq!(alg.length(), 4);
+    assert_eq!(Algorithm::from_str("R U R' U R U2 R'").unwrap().quarter_turn_length(), 8);
 }
 
 #[test]
@@ -156,6 +324,13 @@ fn can_take_inverses() {
     assert_eq!(format!("{}", alg.inverse()), "U R U' R'");
 }
 
+#[test]
+fn can_mirror() {
+    let alg = Algorithm::from_str("R U R' U R U2 R'").unwrap();
+    assert_eq!(format!("{}", alg.mirror()), "L' U' L U' L' U2 L");
+    assert_eq!(format!("{}", alg.mirror().mirror()), "R U R' U R U2 R'");
+}
+
 #[test]
 fn handles_empty_alg() {
     let alg = Algorithm::from_str("").unwrap();
@@ -163,3 +338,42 @@ fn handles_empty_alg() {
     assert_eq!(format!("{}", alg.best_rotation()), "");
     assert_eq!(format!("{}", alg.canonical_rotation()), "");
 }
+
+#[test]
+fn parses_other_notations() {
+    let parse = |s: &str| format!("{}", Algorithm::parse_extended(s).unwrap());
+    assert_eq!(parse("R U R' U R U2 R'"), "R U R' U R U2 R'");
+    assert_eq!(parse("RUR'URU2R'"), "R U R' U R U2 R'");
+    assert_eq!(parse("R U2\u{2019} R2' U3 R4 F"), "R U2 R2 U' F");
+    assert_eq!(parse("(R U R' U')3"), "R U R' U' R U R' U' R U R' U'");
+    assert_eq!(parse("[R U R' U']x2 {F}"), "R U R' U' R U R' U' F");
+    assert_eq!(parse("(R U R' (U')2)"), "R U R' U' U'");
+    // turning the cube brings the back face round to the right
+    assert_eq!(parse("y R U R'"), "B U B'");
+    assert_eq!(parse("y' R y2 F U"), "F R U");
+    // a y in brackets turns the cube again each time round
+    assert_eq!(parse("(R y)2 R"), "R B L");
+    assert_eq!(parse("(y R)2"), "B L");
+    assert_eq!(parse("(y R)0 R"), "R");
+
+    assert!(Algorithm::parse_extended("x R U R'").is_err());
+    assert!(Algorithm::parse_extended("R M' U").is_err());
+    assert!(Algorithm::parse_extended("r U R'").is_err());
+    assert!(Algorithm::parse_extended("(R U R'").is_err());
+    assert!(Algorithm::parse_extended("R U) R'").is_err());
+    assert!(Algorithm::parse_extended("R Q").is_err());
+    assert!(Algorithm::parse_extended("(R U)100000000").is_err());
+    assert!(Algorithm::parse_extended("(R U)99999999999999999999999").is_err());
+    assert!(Algorithm::parse_extended("(((R U)100)100)100").is_err());
+}
+
+#[test]
+fn tells_ll_algs() {
+    let alg = |s: &str| Algorithm::from_str(s).unwrap();
+    assert!(alg("R U R' U R U2 R'").is_ll());
+    assert!(alg("R U R' U R U2 R'").keeps_f2l());
+    assert!(!alg("R U R'").is_ll());
+    assert!(!alg("R U R'").keeps_f2l());
+    assert!(!alg("U2").is_ll());
+    assert!(alg("U2").keeps_f2l());
+}
@@ -5,13 +5,16 @@ use algorithm::Algorithm;
 use ::std::str::FromStr;
 use ::std::error::Error;
 use f2l_cubestate::F2LCubeState;
+use lla_error::LLAError;
 
 #[derive(Debug)]
 pub struct AlgorithmIterator {
     cubestates: Vec<F2LCubeState>,
     moves: Vec<Generator>,
     indices: Vec<usize>,
-    length: i8
+    length: i8,
+    // the longest algs to give, if there's a limit
+    max_length: Option<i8>,
 }
 
 enum IncrementResult {
@@ -27,6 +30,7 @@ impl AlgorithmIterator {
             cubestates: vec![],
             indices: vec![],
             length: 0,
+            max_length: None,
         };
 
         iter.initialize_with_length(6);
@@ -86,9 +90,23 @@ impl AlgorithmIterator {
             cubestates: cubestates,
             indices: indices,
             length: alg.length() as i8,
+            max_length: None,
         })
     }
 
+    // Stops once the algs would get longer than `max` moves, rather than carrying on for ever.
+    pub fn with_max_length(mut self, max: usize) -> Result<Self, LLAError> {
+        if max > i8::max_value() as usize {
+            return Err(LLAError::InvalidOption(format!("Algs can be at most {} moves long, not {}", i8::max_value(), max)));
+        }
+        self.max_length = Some(max as i8);
+        Ok(self)
+    }
+
+    fn past_max_length(&self, length: i8) -> bool {
+        self.max_length.map_or(false, |max| length > max)
+    }
+
     fn push_move(&mut self, g: Generator) {
         self.moves.push(g);
         self.indices.push(0);
@@ -142,13 +160,17 @@ impl AlgorithmIterator {
         IncrementResult::Done
     }
 
-    fn increment_to_next_cube(&mut self) -> F2LCubeState {
+    // None once there's nothing left no longer than the maximum length.
+    fn increment_to_next_cube(&mut self) -> Option<F2LCubeState> {
         let mut cur_idx = self.length as usize - 1;
         loop {
             match self.inc_idx(cur_idx) {
                 IncrementResult::Done => { break },
                 IncrementResult::Exhausted => {
                     let new_length = self.length + 1;
+                    if self.past_max_length(new_length) {
+                        return None;
+                    }
                     self.initialize_with_length(new_length);
                     return self.increment_to_next_cube()
                 },
@@ -157,15 +179,7 @@ impl AlgorithmIterator {
                 }
             }
         }
-        self.cubestates[self.length as usize - 1]
-    }
-
-    fn current_algorithm(&self) -> String {
-        self.moves.iter().map(|m| format!("{}", m)).collect::<Vec<String>>().join(" ")
-    }
-
-    fn current_cube(&self) -> F2LCubeState {
-        self.cubestates[self.cubestates.len() - 1]
+        Some(self.cubestates[self.length as usize - 1])
     }
 
     fn ending_in_u_move(&self) -> bool {
@@ -179,26 +193,21 @@ impl Iterator for AlgorithmIterator {
     type Item = Algorithm;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.increment_to_next_cube();
-        let mut current_cube = self.current_cube();
+        if self.past_max_length(self.length) {
+            return None;
+        }
+        let mut current_cube = self.increment_to_next_cube()?;
 
         while self.ending_in_u_move() || !current_cube.is_ll() {
-            current_cube = self.increment_to_next_cube();
+            current_cube = self.increment_to_next_cube()?;
         }
 
-        let alg = self.current_algorithm();
-
-        // TODO: dont unwrap here
-        let algorithm = Algorithm::from_str(alg.as_str()).unwrap();
-
-        Some(algorithm.best_rotation())
+        Some(Algorithm { moves: self.moves.clone() }.best_rotation())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use test::Bencher;
-
     #[test]
     fn test_6_movers() {
         assert_eq!(
@@ -229,6 +238,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_stops_at_max_length() {
+        let algs: Vec<_> = ::algorithm_iterator::AlgorithmIterator::new().with_max_length(7).unwrap().collect();
+        assert_eq!(algs.len(), 14);
+        assert_eq!(format!("{}", algs[13]), format!("{}", ::algorithm_iterator::AlgorithmIterator::
+            from_starting_algorithm("R' F' U' F U R").unwrap().take(10).last().unwrap()));
+        assert_eq!(::algorithm_iterator::AlgorithmIterator::new().with_max_length(5).unwrap().count(), 0);
+        assert!(::algorithm_iterator::AlgorithmIterator::new().with_max_length(200).is_err());
+    }
+
     #[test]
     fn test_increments_length() {
         let first = ::algorithm_iterator::AlgorithmIterator::
@@ -239,13 +258,4 @@ mod tests {
             "R U R' U R U2 R'"
         );
     }
-
-    //#[bench]
-    fn bench_gen_6s(b: &mut Bencher) {
-        b.iter(|| {
-            for alg in ::algorithm_iterator::AlgorithmIterator::new().take(4) {
-                ::test::black_box(alg);
-            }
-        });
-    }
 }
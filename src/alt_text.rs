@@ -0,0 +1,85 @@
+use algorithm::Algorithm;
+use generator::Face;
+use image_style::sticker_face;
+
+// Describes the picture of a case in words, for people who can't see it. Colours are given by
+// face ("the front colour") rather than by name, as they depend on the colour scheme and that's
+// how cubers would think about it anyway.
+
+// Where each U sticker is, going by the net in cubestate.rs.
+const TOP_POSITIONS: [&'static str; 9] = [
+    "back left corner", "back edge", "back right corner",
+    "left edge", "centre", "right edge",
+    "front left corner", "front edge", "front right corner",
+];
+
+// The stickers on each side of the last layer, left to right as you'd see them looking at that
+// side.
+const SIDES: [(&'static str, [usize; 3]); 4] = [
+    ("Front", [12, 13, 14]),
+    ("Right", [15, 16, 17]),
+    ("Back", [53, 52, 51]),
+    ("Left", [9, 10, 11]),
+];
+
+fn colour_name(f: Face) -> &'static str {
+    match f {
+        Face::U => "top",
+        Face::D => "bottom",
+        Face::F => "front",
+        Face::B => "back",
+        Face::R => "right",
+        Face::L => "left",
+    }
+}
+
+fn list(items: &[&str]) -> String {
+    match items.len() {
+        0 => String::from("none"),
+        1 => String::from(items[0]),
+        n => format!("{} and {}", items[..n - 1].join(", "), items[n - 1]),
+    }
+}
+
+pub fn describe(alg: &Algorithm, case_name: Option<&str>) -> String {
+    let c = alg.inverse().cube();
+    let mut sentences = vec![];
+    sentences.push(match case_name {
+        Some(name) => format!("The {} last layer case, seen from above.", name),
+        None => String::from("A last layer case, seen from above."),
+    });
+
+    let top: Vec<&str> = (0..9).filter(|&i| sticker_face(c.state[i]) == Face::U).map(|i| TOP_POSITIONS[i]).collect();
+    sentences.push(if top.len() == 9 {
+        String::from("The top face is all the top colour.")
+    } else {
+        format!("The top colour is on {} of the 9 top stickers: {}.", top.len(), list(&top))
+    });
+
+    for &(side, ref stickers) in SIDES.iter() {
+        let colours: Vec<&str> = stickers.iter().map(|&i| colour_name(sticker_face(c.state[i]))).collect();
+        sentences.push(if colours.iter().all(|&c| c == colours[0]) {
+            format!("{} side: all {} colour.", side, colours[0])
+        } else {
+            format!("{} side, left to right: {} colours.", side, list(&colours))
+        });
+    }
+    sentences.join(" ")
+}
+
+#[test]
+fn describes_cases() {
+    use std::str::FromStr;
+    let t_perm = Algorithm::from_str("R U R' U' R' F R2 U' R' U' R U R' F'").unwrap();
+    assert_eq!(describe(&t_perm, Some("T perm")),
+               "The T perm last layer case, seen from above. The top face is all the top colour. \
+                Front side, left to right: front, front and right colours. \
+                Right side, left to right: back, left and front colours. \
+                Back side, left to right: right, back and back colours. \
+                Left side, left to right: left, right and left colours.");
+
+    let sune = Algorithm::from_str("R U R' U R U2 R'").unwrap();
+    assert!(describe(&sune, None).starts_with(
+        "A last layer case, seen from above. The top colour is on 6 of the 9 top stickers: back edge, left edge, \
+         centre, right edge, front left corner and front edge."));
+}
@@ -0,0 +1,104 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::Path;
+use gif;
+use gif::SetParameter;
+use png;
+use algorithm::Algorithm;
+use cube_image::generate_isometric_frame;
+use image_generator::ImageOptions;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationOptions {
+    // How many frames each move is drawn with. With 1 there's a frame between each pair of
+    // moves, anything more draws the turns partway through too.
+    pub frames_per_move: usize,
+    // In hundredths of a second, which is what GIFs count in
+    pub frame_delay: u16,
+    // How long to stay on the solved cube at the end before looping
+    pub end_delay: u16,
+    // Keep the frames as numbered PNGs in this directory
+    pub frames_dir: Option<String>,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        AnimationOptions {
+            frames_per_move: 4,
+            frame_delay: 5,
+            end_delay: 100,
+            frames_dir: None,
+        }
+    }
+}
+
+// Draws each frame of the alg being done, starting from the case it solves, and gives back the
+// files they went to along with how long each one is shown for.
+fn render_frames(alg: &Algorithm, dir: &Path, options: &ImageOptions, animation: &AnimationOptions) -> Vec<(String, u16)> {
+    let mut frames = vec![];
+    let mut frame = |cube, turn, delay| {
+        let filename = dir.join(format!("frame{:04}.png", frames.len())).to_string_lossy().into_owned();
+        generate_isometric_frame(cube, turn, filename.as_str(), options);
+        frames.push((filename, delay));
+    };
+
+    let frames_per_move = ::std::cmp::max(animation.frames_per_move, 1);
+    let mut cube = alg.inverse().cube();
+    for m in &alg.moves {
+        for step in 0..frames_per_move {
+            let fraction = step as f64 / frames_per_move as f64;
+            frame(cube, Some((m, fraction)), animation.frame_delay);
+        }
+        cube = cube.apply(&m.effect);
+    }
+    frame(cube, None, animation.end_delay);
+
+    frames
+}
+
+// Reads back a frame cairo wrote, as RGBA pixels.
+fn read_frame(filename: &str) -> Result<(u16, u16, Vec<u8>), Box<Error>> {
+    let decoder = png::Decoder::new(File::open(filename)?);
+    let (info, mut reader) = decoder.read_info()?;
+    let mut buf = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buf)?;
+
+    let pixels = match info.color_type {
+        png::ColorType::RGBA => buf,
+        png::ColorType::RGB => buf.chunks(3).flat_map(|p| vec![p[0], p[1], p[2], 0xFF]).collect(),
+        t => return Err(From::from(format!("Unexpected colour type {:?} in {}", t, filename))),
+    };
+    Ok((info.width as u16, info.height as u16, pixels))
+}
+
+pub fn generate_animation(alg: &Algorithm, output_filename: &str, options: &ImageOptions, animation: &AnimationOptions) -> Result<(), Box<Error>> {
+    let dir = match animation.frames_dir {
+        Some(ref dir) => Path::new(dir).to_path_buf(),
+        None => ::std::env::temp_dir().join(format!("last-layer-algs-{}", ::std::process::id())),
+    };
+    fs::create_dir_all(&dir)?;
+
+    let frames = render_frames(alg, &dir, options, animation);
+
+    let mut encoder = None;
+    for &(ref filename, delay) in frames.iter() {
+        let (width, height, mut pixels) = read_frame(filename.as_str())?;
+        if encoder.is_none() {
+            let mut e = gif::Encoder::new(File::create(output_filename)?, width, height, &[])?;
+            e.set(gif::Repeat::Infinite)?;
+            encoder = Some(e);
+        }
+        let mut frame = gif::Frame::from_rgba(width, height, &mut pixels);
+        frame.delay = delay;
+        // each frame is a whole image, so clear the last one out rather than drawing over it
+        frame.dispose = gif::DisposalMethod::Background;
+        if let Some(ref mut e) = encoder {
+            e.write_frame(&frame)?;
+        }
+    }
+
+    if animation.frames_dir.is_none() {
+        fs::remove_dir_all(&dir)?;
+    }
+    Ok(())
+}
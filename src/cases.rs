@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+use algorithm::Algorithm;
+use cubestate::{CubeState, CORNER_SLOTS, EDGE_SLOTS};
+
+// Telling what sort of last layer case an alg solves, and what it's called.
+
+// Identifies the case an alg solves, the same whichever way round the cube's held when it's
+// done or which way the U layer's turned afterwards. Only the stickers the last layer can move
+// go into it, as a pair of hex digits each.
+pub fn case_key(alg: &Algorithm) -> String {
+    key_of_case(&alg.inverse().cube())
+}
+
+// The same for a case given as the state it leaves the cube in, rather than by an alg for it.
+pub fn key_of_case(case: &CubeState) -> String {
+    let u = Algorithm::from_str("U").unwrap().cube();
+    let mut turns = vec![CubeState::solved()];
+    for i in 1..4 {
+        let turned = turns[i - 1].apply(&u);
+        turns.push(turned);
+    }
+    let stickers = (0..16)
+        .map(|i| {
+            let cube = turns[i / 4].apply(case).apply(&turns[i % 4]);
+            (0..18).chain(51..54).map(|i| cube.state[i]).collect::<Vec<u8>>()
+        })
+        .min()
+        .unwrap();
+    stickers.iter().map(|s| format!("{:02x}", s)).collect()
+}
+
+// The keys of the case an alg solves and of the cases its mirror and inverse solve, which look
+// near enough the same to someone scrolling past.
+pub fn symmetry_keys(alg: &Algorithm) -> Vec<String> {
+    let mirror = alg.mirror();
+    vec![case_key(alg), case_key(&mirror), case_key(&alg.inverse()), case_key(&mirror.inverse())]
+}
+
+// Whether every piece is in its slot, however it's twisted.
+fn pieces_in_place(c: &CubeState) -> bool {
+    CORNER_SLOTS.iter().chain(EDGE_SLOTS.iter()).all(|slot| {
+        let mut stickers: Vec<usize> = slot.iter().map(|&i| c.state[i] as usize).collect();
+        stickers.sort();
+        let mut expected = slot.to_vec();
+        expected.sort();
+        stickers == expected
+    })
+}
+
+fn oriented(c: &CubeState, slots: &[&[usize]]) -> bool {
+    slots.iter().all(|slot| c.state[slot[0]] < 9)
+}
+
+// Which step of the usual ways of doing the last layer the case is:
+//
+//  * "AUF" if all it needs is a U turn
+//  * "PLL" if everything's oriented
+//  * "OLL" if everything's in place, up to a U turn, and it just needs orienting
+//  * "ZBLL" if the edges are oriented
+//  * "1LLL" for anything else
+pub fn subset(alg: &Algorithm) -> &'static str {
+    subset_of_case(&alg.inverse().cube())
+}
+
+pub fn subset_of_case(case: &CubeState) -> &'static str {
+    let case = *case;
+    let u = Algorithm::from_str("U").unwrap().moves[0];
+    let mut permuted = false;
+    let mut c = case;
+    for _ in 0..4 {
+        permuted = permuted || pieces_in_place(&c);
+        c = c.apply(&u.effect);
+    }
+    let edges_oriented = oriented(&case, &EDGE_SLOTS);
+    let corners_oriented = oriented(&case, &CORNER_SLOTS);
+
+    match (edges_oriented && corners_oriented, permuted) {
+        (true, true) => "AUF",
+        (true, false) => "PLL",
+        (false, true) => "OLL",
+        (false, false) if edges_oriented => "ZBLL",
+        _ => "1LLL",
+    }
+}
+
+// The U turns an alg needs before and after it to solve a case, in quarter turns clockwise.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Aufs {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl Aufs {
+    // The alg with the turns around it.
+    pub fn around(&self, alg: &Algorithm) -> Algorithm {
+        let turns = |n: usize| Algorithm::from_str(["", "U", "U2", "U'"][n]).unwrap().moves;
+        let mut moves = turns(self.before);
+        moves.extend(alg.moves.iter().cloned());
+        moves.extend(turns(self.after));
+        Algorithm { moves: moves }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Solves(Aufs),
+    // it doesn't, and these AUFs leave the fewest stickers out of place, this state being what's
+    // left to solve
+    Leaves(Aufs, CubeState),
+}
+
+fn misplaced(c: &CubeState) -> usize {
+    c.state.iter().enumerate().filter(|&(i, &s)| s as usize != i).count()
+}
+
+// Whether `alg` solves the case `setup` leaves a solved cube in, allowing for turns of the U
+// layer before and after it.
+pub fn verify(alg: &Algorithm, setup: &Algorithm) -> Verdict {
+    let case = setup.cube();
+    let (aufs, left) = (0..4).flat_map(|before| (0..4).map(move |after| Aufs { before: before, after: after }))
+        .map(|aufs| (aufs, case.apply(&aufs.around(alg).cube())))
+        .min_by_key(|&(_, ref left)| misplaced(left))
+        .unwrap();
+    if misplaced(&left) == 0 {
+        Verdict::Solves(aufs)
+    } else {
+        Verdict::Leaves(aufs, left)
+    }
+}
+
+// Names for cases, read from a list in the same format `sheet` takes, e.g.
+//
+//   Sune,R U R' U R U2 R'
+//   T perm,R U R' U' R' F R2 U' R' U' R U R' F'
+//
+// An alg from a list of them, with the name it was given there if it has one.
+pub struct AlgEntry {
+    pub alg: Algorithm,
+    pub name: Option<String>,
+}
+
+// Reads a list of algs, one per line, each optionally preceded by a name and a comma:
+//
+//   Sune,R U R' U R U2 R'
+//   R U2 R' U' R U' R'
+//
+// Blank lines and lines starting with # are skipped.
+pub fn parse_entries(s: &str) -> Result<Vec<AlgEntry>, Box<Error>> {
+    let mut result = vec![];
+    for line in s.lines().map(|l| l.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = match line.find(',') {
+            Some(idx) => AlgEntry {
+                alg: Algorithm::from_str(&line[idx + 1..])?,
+                name: Some(String::from(line[..idx].trim())),
+            },
+            None => AlgEntry { alg: Algorithm::from_str(line)?, name: None },
+        };
+        result.push(entry);
+    }
+    Ok(result)
+}
+
+// Any alg for a case will do, the name goes with the case and not the alg.
+pub struct CaseNames {
+    names: HashMap<String, String>,
+}
+
+impl CaseNames {
+    pub fn empty() -> Self {
+        CaseNames { names: HashMap::new() }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, Box<Error>> {
+        let mut names = HashMap::new();
+        for entry in parse_entries(s)? {
+            if let Some(name) = entry.name {
+                names.insert(case_key(&entry.alg), name);
+            }
+        }
+        Ok(CaseNames { names: names })
+    }
+
+    pub fn name(&self, alg: &Algorithm) -> Option<&str> {
+        self.name_of_key(case_key(alg).as_str())
+    }
+
+    pub fn name_of_key(&self, key: &str) -> Option<&str> {
+        self.names.get(key).map(|n| n.as_str())
+    }
+}
+
+#[test]
+fn parses_entries() {
+    let entries = parse_entries("
+        # some OLLs
+        Sune,R U R' U R U2 R'
+        R U2 R' U' R U' R'
+    ").unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].name, Some(String::from("Sune")));
+    assert_eq!(format!("{}", entries[0].alg), "R U R' U R U2 R'");
+    assert_eq!(entries[1].name, None);
+    assert!(parse_entries("Sune,R U X").is_err());
+}
+
+#[test]
+fn same_case_whatever_the_aufs() {
+    let sune = Algorithm::from_str("R U R' U R U2 R'").unwrap();
+    let key = case_key(&sune);
+    assert_eq!(key.len(), 42);
+    assert_eq!(case_key(&Algorithm::from_str("U R U R' U R U2 R' U2").unwrap()), key);
+    // the same alg done from the back
+    assert_eq!(case_key(&Algorithm::from_str("L U L' U L U2 L'").unwrap()), key);
+    assert!(case_key(&Algorithm::from_str("R U2 R' U' R U' R'").unwrap()) != key);
+}
+
+#[test]
+fn spots_symmetry_variants() {
+    let sune = Algorithm::from_str("R U R' U R U2 R'").unwrap();
+    let left_sune = Algorithm::from_str("L' U' L U' L' U2 L").unwrap();
+    let antisune = Algorithm::from_str("R U2 R' U' R U' R'").unwrap();
+    let t_perm = Algorithm::from_str("R U R' U' R' F R2 U' R' U' R U R' F'").unwrap();
+    assert!(symmetry_keys(&left_sune).contains(&case_key(&sune)));
+    assert!(symmetry_keys(&antisune).contains(&case_key(&sune)));
+    assert!(!symmetry_keys(&t_perm).contains(&case_key(&sune)));
+}
+
+#[test]
+fn tells_subsets_apart() {
+    let subset_of = |s| subset(&Algorithm::from_str(s).unwrap());
+    assert_eq!(subset_of("U"), "AUF");
+    assert_eq!(subset_of("R U R' U' R' F R2 U' R' U' R U R' F'"), "PLL");
+    assert_eq!(subset_of("R U R' U R U2 R'"), "ZBLL");
+    assert_eq!(subset_of("F R U R' U' F'"), "1LLL");
+    // twists two corners and nothing else
+    assert_eq!(subset_of("R' D' R D R' D' R D U R' D' R D R' D' R D R' D' R D R' D' R D"), "OLL");
+}
+
+#[test]
+fn names_cases_whatever_the_alg() {
+    let names = CaseNames::parse("Sune,R U R' U R U2 R'\nR U2 R' U' R U' R'").unwrap();
+    assert_eq!(names.name(&Algorithm::from_str("U L U L' U L U2 L'").unwrap()), Some("Sune"));
+    assert_eq!(names.name(&Algorithm::from_str("R U2 R' U' R U' R'").unwrap()), None);
+}
+
+#[test]
+fn verifies_algs() {
+    let alg = |s| Algorithm::from_str(s).unwrap();
+    let sune = alg("R U R' U R U2 R'");
+    assert_eq!(verify(&sune, &sune.inverse()), Verdict::Solves(Aufs { before: 0, after: 0 }));
+    // the same case, set up from another angle and left turned
+    let aufs = match verify(&sune, &alg("U R U2 R' U' R U' R' U2")) {
+        Verdict::Solves(aufs) => aufs,
+        v => panic!("{:?}", v),
+    };
+    assert_eq!(aufs, Aufs { before: 2, after: 3 });
+    assert_eq!(format!("{}", aufs.around(&sune)), "U2 R U R' U R U2 R' U'");
+
+    match verify(&alg("R U2 R' U' R U' R'"), &sune.inverse()) {
+        Verdict::Leaves(_, left) => assert!(left != CubeState::solved()),
+        v => panic!("{:?}", v),
+    }
+}
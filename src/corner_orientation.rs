@@ -148,6 +148,21 @@ impl Prunable for Orientation {
     fn total_states() -> usize { NUM_ORIENTATIONS }
 }
 
+pub fn make_tables() -> (Vec<[usize; 18]>, Vec<u16>) {
+    (Orientation::make_transition_table(), Orientation::make_pruning_table())
+}
+
+// Twists solved corners by each of the moves in turn, working out each one, for the benchmarks.
+// Gives back the orientation's index.
+pub fn twist_corners(moves: &[usize]) -> COIndex {
+    moves.iter().fold(Orientation::initial_pos(), |o, &m| o.apply_idx(m)).index()
+}
+
+// The same, looking each one up in the table.
+pub fn twist_corners_by_table(moves: &[usize]) -> COIndex {
+    moves.iter().fold(CO_SOLVED, |index, &m| CO_TRANSITIONS[index][m])
+}
+
 // TODO: this is copied from corner_permutation, we should find a nice way to generalize it
 lazy_static! {
     pub static ref CO_TRANSITIONS: Vec<[usize; 18]> = {
@@ -174,82 +189,6 @@ lazy_static! {
 //     assert_eq!(0b0001100000000000, permute_by_move_index(0b0000011000000000, 0));
 // }
 
-// use test::Bencher;
-
-// #[bench]
-// fn int_bench(b: &mut Bencher) {
-//     b.iter(|| {
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//         ::test::black_box(add(0b1010100101010000, 0b0110011000100110));
-//     });
-// }
-
-
-
-// #[bench]
-// fn array_bench(bench: &mut Bencher) {
-//     let mut c = [0, 0, 0, 0, 0, 0, 0, 0];
-//     let mut a = [0, 1, 2, 0, 1, 2, 0, 1];
-//     let mut b = [1, 0, 2, 2, 2, 1, 0, 1];
-//     bench.iter(|| {
-//         add_into(a, b, &mut c);
-//         add_into(c, a, &mut b);
-//         add_into(b, c, &mut a);
-//         add_into(a, b, &mut c);
-//         add_into(a, b, &mut c);
-//         add_into(a, b, &mut c);
-//         add_into(a, b, &mut c);
-//         add_into(a, b, &mut c);
-//         add_into(a, b, &mut c);
-//         add_into(a, b, &mut c);
-//         add_into(a, b, &mut c);
-//         add_into(c, a, &mut b);
-//         add_into(b, c, &mut a);
-//         add_into(c, a, &mut b);
-//         add_into(b, c, &mut a);
-//         add_into(c, a, &mut b);
-//         add_into(b, c, &mut a);
-//         add_into(c, a, &mut b);
-//         add_into(b, c, &mut a);
-//         add_into(c, a, &mut b);
-//         add_into(b, c, &mut a);
-//         add_into(c, a, &mut b);
-//         add_into(b, c, &mut a);
-//         add_into(c, a, &mut b);
-//         add_into(b, c, &mut a);
-//         add_into(c, a, &mut b);
-//         add_into(b, c, &mut a);
-//         ::test::black_box(c);
-//         ::test::black_box(a);
-//         ::test::black_box(b);
-//     });
-// }
 // type Orientation = u16;
 
 // const FIRSTS : u16 = 0b1010101010101010;
@@ -102,6 +102,10 @@ impl Prunable for CornerPermutation {
     fn total_states() -> usize { NUM_PERMUTATIONS }
 }
 
+pub fn make_tables() -> (Vec<[usize; 18]>, Vec<u16>) {
+    (CornerPermutation::make_transition_table(), CornerPermutation::make_pruning_table())
+}
+
 lazy_static! {
     pub static ref TRANSITIONS: Vec<[usize; 18]> = {
         CornerPermutation::make_transition_table()
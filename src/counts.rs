@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+use algorithm::Algorithm;
+use cases::case_key;
+
+// Tallying the enumeration by length, for `count`: how many algs there are of each length, how
+// many cases they solve, and how many cases can be solved in that many moves or fewer.
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LengthCounts {
+    pub length: usize,
+    pub algs: usize,
+    pub cases: usize,
+    // cases with no shorter alg
+    pub new_cases: usize,
+    // cases with an alg this long or shorter
+    pub cases_so_far: usize,
+}
+
+// Takes the algs in order of length, as the enumeration gives them, so that each length's counts
+// are done as soon as the first longer alg comes along.
+pub struct LengthCounter {
+    current: Option<LengthCounts>,
+    cases_this_length: HashSet<String>,
+    cases_seen: HashSet<String>,
+}
+
+impl LengthCounter {
+    pub fn new() -> Self {
+        LengthCounter { current: None, cases_this_length: HashSet::new(), cases_seen: HashSet::new() }
+    }
+
+    // Counts the alg, giving back the counts for the length before it if it's the first alg
+    // longer than them.
+    pub fn add(&mut self, alg: &Algorithm) -> Option<LengthCounts> {
+        let length = alg.length() as usize;
+        let finished = match self.current {
+            Some(counts) if counts.length != length => self.finish_length(),
+            _ => None,
+        };
+        let mut counts = self.current.unwrap_or(LengthCounts {
+            length: length,
+            algs: 0,
+            cases: 0,
+            new_cases: 0,
+            cases_so_far: self.cases_seen.len(),
+        });
+        let key = case_key(alg);
+        counts.algs += 1;
+        if self.cases_this_length.insert(key.clone()) {
+            counts.cases += 1;
+        }
+        if self.cases_seen.insert(key) {
+            counts.new_cases += 1;
+            counts.cases_so_far += 1;
+        }
+        self.current = Some(counts);
+        finished
+    }
+
+    fn finish_length(&mut self) -> Option<LengthCounts> {
+        self.cases_this_length.clear();
+        self.current.take()
+    }
+
+    // The counts for the last length.
+    pub fn finish(mut self) -> Option<LengthCounts> {
+        self.finish_length()
+    }
+}
+
+#[test]
+fn counts_by_length() {
+    use std::str::FromStr;
+    let algs = ["F R U R' U' F'", "F U R U' R' F'", "R U R' U R U2 R'", "L' U' L U' L' U2 L", "U R U R' U R U2 R'"];
+    let mut counter = LengthCounter::new();
+    let mut rows = vec![];
+    for alg in algs.iter() {
+        rows.extend(counter.add(&Algorithm::from_str(alg).unwrap()));
+    }
+    rows.extend(counter.finish());
+    assert_eq!(rows, vec![
+        LengthCounts { length: 6, algs: 2, cases: 2, new_cases: 2, cases_so_far: 2 },
+        LengthCounts { length: 7, algs: 2, cases: 2, new_cases: 2, cases_so_far: 4 },
+        // the same case as the Sune before it
+        LengthCounts { length: 8, algs: 1, cases: 1, new_cases: 0, cases_so_far: 4 },
+    ]);
+}
@@ -0,0 +1,293 @@
+use cairo::surface::Surface;
+use cairo::Cairo;
+use cairo::surface::format::Format;
+use cubestate::CubeState;
+use generator::{Face, Generator, Modifier, GENERATORS};
+use image_generator::{draw_polygon, set_up_canvas, ImageOptions};
+use image_style::sticker_face;
+
+// Images of the whole cube, rather than just the last layer. The arrow options only make sense
+// for the last layer view so they're ignored here.
+
+const CELL_SIZE: f64 = 44.;
+const STICKER_SIZE: f64 = 40.;
+const MARGIN: f64 = 10.;
+
+// The net is laid out like the diagram in cubestate.rs, 9 stickers wide and 12 tall.
+const NET_COLUMNS: usize = 9;
+const NET_ROWS: usize = 12;
+
+// The sticker drawn at the given row and column of the net, if any.
+fn net_sticker(row: usize, col: usize) -> Option<usize> {
+    let in_middle_columns = col >= 3 && col < 6;
+    match row {
+        0 ... 2 if in_middle_columns => Some(row * 3 + col - 3),
+        3 ... 5 => Some(9 + (row - 3) * 9 + col),
+        6 ... 8 if in_middle_columns => Some(36 + (row - 6) * 3 + col - 3),
+        9 ... 11 if in_middle_columns => Some(45 + (row - 9) * 3 + col - 3),
+        _ => None,
+    }
+}
+
+pub fn generate_net_image(c: CubeState, output_filename: &str, options: &ImageOptions) {
+    let width = NET_COLUMNS as f64 * CELL_SIZE + 2. * MARGIN;
+    let height = NET_ROWS as f64 * CELL_SIZE + 2. * MARGIN;
+
+    let style = &options.style;
+    let (image_width, image_height) = style.image_size(width, height);
+    let mut surface = Surface::create_image(Format::ARGB32, image_width, image_height);
+    let mut cr = Cairo::create(&mut surface);
+    set_up_canvas(&mut cr, style, width, height);
+
+    for row in 0..NET_ROWS {
+        for col in 0..NET_COLUMNS {
+            if let Some(i) = net_sticker(row, col) {
+                let x = MARGIN + col as f64 * CELL_SIZE + (CELL_SIZE - STICKER_SIZE) / 2.;
+                let y = MARGIN + row as f64 * CELL_SIZE + (CELL_SIZE - STICKER_SIZE) / 2.;
+                draw_polygon(&mut cr, &[
+                    (x, y),
+                    (x + STICKER_SIZE, y),
+                    (x + STICKER_SIZE, y + STICKER_SIZE),
+                    (x, y + STICKER_SIZE),
+                ], style.colors.rgb(options.stickering.face(c.state[i])));
+            }
+        }
+    }
+
+    surface.write_to_png(output_filename);
+}
+
+// The isometric view looks at the cube from the UFR corner. Points on the cube are given as
+// (x, y, z) with x going from L to R, y from B to F and z from D to U, each from 0 to 3.
+type Point = (f64, f64, f64);
+
+const ISO_UNIT: f64 = 40.;
+// How much of each sticker's cell it fills
+const ISO_STICKER_SCALE: f64 = 0.88;
+// Where the viewer is looking from
+const VIEW: Point = (1., 1., 1.);
+const CENTER: f64 = 1.5;
+
+fn iso_dimensions() -> (f64, f64) {
+    let cos30 = (30f64).to_radians().cos();
+    (6. * ISO_UNIT * cos30 + 2. * MARGIN, 6. * ISO_UNIT + 2. * MARGIN)
+}
+
+fn project((x, y, z): Point) -> (f64, f64) {
+    let (width, height) = iso_dimensions();
+    let angle = (30f64).to_radians();
+    (width / 2. + (x - y) * ISO_UNIT * angle.cos(),
+     height / 2. + (x + y) * ISO_UNIT * angle.sin() - z * ISO_UNIT)
+}
+
+fn dot(a: Point, b: Point) -> f64 {
+    a.0 * b.0 + a.1 * b.1 + a.2 * b.2
+}
+
+fn face_normal(f: Face) -> Point {
+    match f {
+        Face::U => (0., 0., 1.),
+        Face::D => (0., 0., -1.),
+        Face::F => (0., 1., 0.),
+        Face::B => (0., -1., 0.),
+        Face::R => (1., 0., 0.),
+        Face::L => (-1., 0., 0.),
+    }
+}
+
+// The corners of a sticker, starting at `origin` and spanning `a` and `b`.
+fn quad((ox, oy, oz): Point, a: Point, b: Point) -> [Point; 4] {
+    [
+        (ox, oy, oz),
+        (ox + a.0, oy + a.1, oz + a.2),
+        (ox + a.0 + b.0, oy + a.1 + b.1, oz + a.2 + b.2),
+        (ox + b.0, oy + b.1, oz + b.2),
+    ]
+}
+
+// Where each sticker of the cubestate.rs diagram sits on the solved cube.
+fn sticker_corners(i: usize) -> [Point; 4] {
+    let (face_start, row, col) = match i {
+        0 ... 8 => (0, i / 3, i % 3),
+        9 ... 35 => {
+            let face_start = 9 + (i - 9) % 9 / 3 * 3;
+            (face_start, (i - 9) / 9, (i - face_start) % 9)
+        },
+        36 ... 44 => (36, (i - 36) / 3, (i - 36) % 3),
+        _ => (45, (i - 45) / 3, (i - 45) % 3),
+    };
+    let (r, c) = (row as f64, col as f64);
+    match face_start {
+        // rows go from B to F, columns from L to R
+        0 => quad((c, r, 3.), (1., 0., 0.), (0., 1., 0.)),
+        // rows go from U to D, columns from B to F
+        9 => quad((0., c, 3. - r), (0., 1., 0.), (0., 0., -1.)),
+        // rows go from U to D, columns from L to R
+        12 => quad((c, 3., 3. - r), (1., 0., 0.), (0., 0., -1.)),
+        // rows go from U to D, columns from F to B
+        15 => quad((3., 3. - c, 3. - r), (0., -1., 0.), (0., 0., -1.)),
+        // rows go from F to B, columns from L to R
+        36 => quad((c, 3. - r, 0.), (1., 0., 0.), (0., -1., 0.)),
+        // rows go from D to U, columns from L to R
+        _ => quad((c, 0., r), (1., 0., 0.), (0., 0., 1.)),
+    }
+}
+
+fn centroid(points: &[Point; 4]) -> Point {
+    let n = points.len() as f64;
+    (points.iter().map(|p| p.0).sum::<f64>() / n,
+     points.iter().map(|p| p.1).sum::<f64>() / n,
+     points.iter().map(|p| p.2).sum::<f64>() / n)
+}
+
+// Whether the point is in the layer that turns when `f` is turned.
+fn in_layer(f: Face, (x, y, z): Point) -> bool {
+    match f {
+        Face::U => z > 2.,
+        Face::D => z < 1.,
+        Face::F => y > 2.,
+        Face::B => y < 1.,
+        Face::R => x > 2.,
+        Face::L => x < 1.,
+    }
+}
+
+// Rotates the point about the axis through the middle of face `f`, clockwise as seen looking
+// at `f` for positive angles.
+fn rotate(f: Face, angle: f64, (x, y, z): Point) -> Point {
+    let angle = match f {
+        Face::U | Face::R | Face::F => angle,
+        Face::D | Face::L | Face::B => -angle,
+    };
+    let (sin, cos) = (angle.sin(), angle.cos());
+    let turn = |a: f64, b: f64| {
+        (CENTER + (a - CENTER) * cos - (b - CENTER) * sin,
+         CENTER + (a - CENTER) * sin + (b - CENTER) * cos)
+    };
+    match f {
+        Face::U | Face::D => { let (x, y) = turn(x, y); (x, y, z) },
+        Face::R | Face::L => { let (y, z) = turn(y, z); (x, y, z) },
+        Face::F | Face::B => { let (z, x) = turn(z, x); (x, y, z) },
+    }
+}
+
+// The rotation `g` makes, in radians.
+pub fn turn_angle(g: &Generator) -> f64 {
+    let quarter = ::std::f64::consts::PI / 2.;
+    match g.components().1 {
+        Modifier::Normal => quarter,
+        Modifier::Twice => 2. * quarter,
+        Modifier::Prime => -quarter,
+    }
+}
+
+// Pairs each sticker the viewer can see with its corners on the image, furthest away first so
+// that nearer stickers get drawn over them. `turn` is a move along with how far through it we
+// are, from 0 to 1.
+fn iso_stickers(turn: Option<(&Generator, f64)>) -> Vec<(usize, Vec<(f64, f64)>)> {
+    let mut visible = vec![];
+    for i in 0..54 {
+        let mut corners = sticker_corners(i);
+        let mut normal = face_normal(sticker_face(i as u8));
+        if let Some((g, fraction)) = turn {
+            if in_layer(g.face, centroid(&corners)) {
+                let angle = turn_angle(g) * fraction;
+                for p in corners.iter_mut() {
+                    *p = rotate(g.face, angle, *p);
+                }
+                let (nx, ny, nz) = rotate(g.face, angle, (normal.0 + CENTER, normal.1 + CENTER, normal.2 + CENTER));
+                normal = (nx - CENTER, ny - CENTER, nz - CENTER);
+            }
+        }
+        if dot(normal, VIEW) > 1e-9 {
+            visible.push((dot(centroid(&corners), VIEW), i, corners));
+        }
+    }
+    visible.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    visible.iter().map(|&(_, i, ref corners)| {
+        let points: Vec<(f64, f64)> = corners.iter().map(|&p| project(p)).collect();
+        // shrink towards the middle to leave gaps between stickers
+        let cx = points.iter().map(|p| p.0).sum::<f64>() / 4.;
+        let cy = points.iter().map(|p| p.1).sum::<f64>() / 4.;
+        (i, points.iter()
+            .map(|&(x, y)| (cx + (x - cx) * ISO_STICKER_SCALE, cy + (y - cy) * ISO_STICKER_SCALE))
+            .collect())
+    }).collect()
+}
+
+// Draws the isometric view partway through turning `g`, `fraction` of the way.
+pub fn generate_isometric_frame(c: CubeState, turn: Option<(&Generator, f64)>, output_filename: &str, options: &ImageOptions) {
+    let (width, height) = iso_dimensions();
+
+    let style = &options.style;
+    let (image_width, image_height) = style.image_size(width, height);
+    let mut surface = Surface::create_image(Format::ARGB32, image_width, image_height);
+    let mut cr = Cairo::create(&mut surface);
+    set_up_canvas(&mut cr, style, width, height);
+
+    for &(i, ref points) in iso_stickers(turn).iter() {
+        draw_polygon(&mut cr, points, style.colors.rgb(options.stickering.face(c.state[i])));
+    }
+
+    surface.write_to_png(output_filename);
+}
+
+pub fn generate_isometric_image(c: CubeState, output_filename: &str, options: &ImageOptions) {
+    generate_isometric_frame(c, None, output_filename, options);
+}
+
+#[test]
+fn net_has_every_sticker_once() {
+    let mut seen = vec![];
+    for row in 0..NET_ROWS {
+        for col in 0..NET_COLUMNS {
+            if let Some(i) = net_sticker(row, col) {
+                seen.push(i);
+            }
+        }
+    }
+    seen.sort();
+    assert_eq!(seen, (0..54).collect::<Vec<usize>>());
+}
+
+#[test]
+fn isometric_view_shows_three_faces() {
+    let stickers = iso_stickers(None);
+    assert_eq!(stickers.len(), 27);
+    // the U, F and R centres
+    assert!(stickers.iter().any(|&(i, _)| i == 4));
+    assert!(stickers.iter().any(|&(i, _)| i == 22));
+    assert!(stickers.iter().any(|&(i, _)| i == 25));
+}
+
+#[test]
+fn turns_match_generators() {
+    // Turning a sticker all the way through a move should land it where the move's effect
+    // says it goes.
+    let close = |a: Point, b: Point| (a.0 - b.0).abs() + (a.1 - b.1).abs() + (a.2 - b.2).abs() < 1e-6;
+    for g in GENERATORS.iter() {
+        for i in 0..54 {
+            let from = g.effect.state[i] as usize;
+            let start = centroid(&sticker_corners(from));
+            let end = if in_layer(g.face, start) {
+                rotate(g.face, turn_angle(g), start)
+            } else {
+                start
+            };
+            assert!(close(end, centroid(&sticker_corners(i))), "{} moves sticker {} wrong", g, from);
+        }
+    }
+}
+
+#[test]
+fn turning_shows_other_stickers() {
+    use std::str::FromStr;
+    let u = Generator::from_str("U").unwrap();
+    let stickers = iso_stickers(Some((&u, 0.75)));
+    // most of the way through a U the top layer's B stickers come into view, and its F
+    // stickers have turned away
+    assert!(stickers.iter().any(|&(i, _)| i == 52));
+    assert!(!stickers.iter().any(|&(i, _)| i == 13));
+    assert!(stickers.iter().any(|&(i, _)| i == 22));
+}
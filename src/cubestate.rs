@@ -29,6 +29,10 @@ const NUM_STICKERS: usize = 54;
 //          │51│52│53│
 //          └──┴──┴──┘
 
+// The U layer slots, each given as its U sticker followed by its stickers on the side faces.
+pub const CORNER_SLOTS: [&'static [usize]; 4] = [&[0, 9, 51], &[2, 53, 17], &[8, 15, 14], &[6, 12, 11]];
+pub const EDGE_SLOTS: [&'static [usize]; 4] = [&[1, 52], &[5, 16], &[7, 13], &[3, 10]];
+
 #[derive(Copy)]
 pub struct CubeState {
     pub state: [u8; NUM_STICKERS],
@@ -0,0 +1,128 @@
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rand::{self, Rng};
+use schedule::{Schedule, format_time};
+
+// Running the bot on a schedule, for `serve`, rather than once per run from cron.
+
+// Where the time comes from, so that tests don't have to wait for it.
+pub trait Clock {
+    // seconds since the epoch
+    fn now(&self) -> u64;
+    fn sleep_until(&self, t: u64);
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+    }
+
+    fn sleep_until(&self, t: u64) {
+        // in steps, so that a clock change doesn't throw it too far out
+        loop {
+            let now = self.now();
+            if now >= t {
+                return;
+            }
+            thread::sleep(Duration::from_secs(::std::cmp::min(t - now, 60)));
+        }
+    }
+}
+
+// What gets done on each go. Getting it ready is done as soon as the last post is out, so
+// that it's all waiting when the time comes.
+pub trait Job {
+    // Gives back a description of what's going to be posted.
+    fn prepare(&mut self) -> Result<String, Box<Error>>;
+    fn post(&mut self) -> Result<(), Box<Error>>;
+}
+
+fn log(clock: &Clock, message: &str) {
+    println!("[{}] {}", format_time(clock.now()), message);
+}
+
+// Waits for the schedule's next time after now, plus `delay` seconds, and posts. Nothing that goes
+// wrong with the job stops it, it's logged and left to the next go; whatever didn't get posted
+// stays pending and gets another try then. Gives back when it was due.
+pub fn run_once(clock: &Clock, schedule: &Schedule, delay: u64, job: &mut Job) -> Result<u64, Box<Error>> {
+    let due = schedule.next_after(clock.now()).ok_or("The schedule never comes round")? + delay;
+    log(clock, format!("Next post due at {}", format_time(due)).as_str());
+    match job.prepare() {
+        Ok(what) => log(clock, format!("Ready to post {}", what).as_str()),
+        Err(why) => log(clock, format!("Couldn't get the next post ready, trying again when it's due: {}", why).as_str()),
+    }
+    clock.sleep_until(due);
+    match job.post() {
+        Ok(()) => log(clock, "Posted"),
+        Err(why) => log(clock, format!("Couldn't post, it'll be tried again next time: {}", why).as_str()),
+    }
+    Ok(due)
+}
+
+// Posts on the schedule for as long as it keeps running, each time up to `jitter` seconds late
+// so that it doesn't look quite so much like a bot.
+pub fn run(clock: &Clock, schedule: &Schedule, jitter: u64, job: &mut Job) -> Result<(), Box<Error>> {
+    let mut rng = rand::thread_rng();
+    loop {
+        run_once(clock, schedule, rng.gen_range(0, jitter + 1), job)?;
+    }
+}
+
+#[cfg(test)]
+struct FakeClock {
+    now: ::std::cell::Cell<u64>,
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+
+    fn sleep_until(&self, t: u64) {
+        self.now.set(::std::cmp::max(self.now.get(), t));
+    }
+}
+
+#[cfg(test)]
+struct FakeJob {
+    events: Vec<&'static str>,
+    failing: bool,
+}
+
+#[cfg(test)]
+impl Job for FakeJob {
+    fn prepare(&mut self) -> Result<String, Box<Error>> {
+        self.events.push("prepare");
+        if self.failing { Err(From::from("no")) } else { Ok(String::from("R U R' U R U2 R'")) }
+    }
+
+    fn post(&mut self) -> Result<(), Box<Error>> {
+        self.events.push("post");
+        if self.failing { Err(From::from("no")) } else { Ok(()) }
+    }
+}
+
+#[test]
+fn posts_on_schedule() {
+    use std::str::FromStr;
+    // 2017-07-14T02:40:00Z
+    let clock = FakeClock { now: ::std::cell::Cell::new(1_500_000_000) };
+    let schedule = Schedule::from_str("0 12 * * *").unwrap();
+    let mut job = FakeJob { events: vec![], failing: false };
+
+    assert_eq!(format_time(run_once(&clock, &schedule, 0, &mut job).unwrap()), "2017-07-14T12:00:00Z");
+    assert_eq!(format_time(clock.now()), "2017-07-14T12:00:00Z");
+    assert_eq!(format_time(run_once(&clock, &schedule, 90, &mut job).unwrap()), "2017-07-15T12:01:30Z");
+    assert_eq!(job.events, vec!["prepare", "post", "prepare", "post"]);
+
+    // failures wait for the next time round
+    let mut job = FakeJob { events: vec![], failing: true };
+    assert_eq!(format_time(run_once(&clock, &schedule, 0, &mut job).unwrap()), "2017-07-16T12:00:00Z");
+    assert_eq!(job.events, vec!["prepare", "post"]);
+
+    assert!(run_once(&clock, &Schedule::from_str("0 0 30 2 *").unwrap(), 0, &mut job).is_err());
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+use rusqlite::{Connection, ToSql, NO_PARAMS};
+use algorithm::Algorithm;
+use cases::{CaseNames, subset};
+use record::AlgRecord;
+
+// Enumerated algs kept in an SQLite database, so that questions like "every alg for this ZBLL
+// case under 14 moves" are a query rather than a grep through a huge text dump:
+//
+//   SELECT algs.alg FROM algs
+//     JOIN alg_cases ON alg_cases.alg_id = algs.id
+//     JOIN cases ON cases.id = alg_cases.case_id
+//   WHERE cases.name = 'Sune' AND algs.htm < 14;
+//
+// Adding an alg or case that's already there does nothing, so the same database can be added to
+// over several runs.
+
+const SCHEMA: &'static str = "
+    CREATE TABLE IF NOT EXISTS algs (
+        id INTEGER PRIMARY KEY,
+        alg TEXT NOT NULL UNIQUE,
+        htm INTEGER NOT NULL,
+        qtm INTEGER NOT NULL,
+        stm INTEGER NOT NULL,
+        canonical TEXT NOT NULL,
+        inverse TEXT NOT NULL,
+        -- where each sticker ends up, as space separated numbers
+        state TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS cases (
+        id INTEGER PRIMARY KEY,
+        case_key TEXT NOT NULL UNIQUE,
+        subset TEXT NOT NULL,
+        name TEXT
+    );
+    CREATE TABLE IF NOT EXISTS alg_cases (
+        alg_id INTEGER NOT NULL REFERENCES algs (id),
+        case_id INTEGER NOT NULL REFERENCES cases (id),
+        PRIMARY KEY (alg_id, case_id)
+    );
+    -- how far enumerating has got, as algs come in from imports in no particular order
+    CREATE TABLE IF NOT EXISTS progress (
+        name TEXT PRIMARY KEY,
+        alg TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS algs_by_htm ON algs (htm);
+    CREATE INDEX IF NOT EXISTS algs_by_qtm ON algs (qtm);
+    CREATE INDEX IF NOT EXISTS cases_by_name ON cases (name);
+    CREATE INDEX IF NOT EXISTS cases_by_subset ON cases (subset);
+    CREATE INDEX IF NOT EXISTS alg_cases_by_case ON alg_cases (case_id, alg_id);
+";
+
+// How many algs go in each transaction. One each would be far too slow, and all of them in one
+// would lose the lot if it's stopped part way.
+const BATCH_SIZE: usize = 10000;
+
+pub struct AlgDatabase {
+    conn: Connection,
+    // ids of the cases seen so far, to save looking them up for every alg
+    case_ids: HashMap<String, i64>,
+    in_batch: usize,
+    // the last enumerated alg added, to be saved along with the batch it's in
+    enumerated_to: Option<String>,
+}
+
+impl AlgDatabase {
+    // Opens the database, making it and its tables if they aren't there yet.
+    pub fn open(filename: &str) -> Result<Self, Box<Error>> {
+        let conn = Connection::open(filename)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(AlgDatabase { conn: conn, case_ids: HashMap::new(), in_batch: 0, enumerated_to: None })
+    }
+
+    fn case_id(&mut self, alg: &Algorithm, record: &AlgRecord) -> Result<i64, Box<Error>> {
+        if let Some(&id) = self.case_ids.get(&record.case_key) {
+            return Ok(id);
+        }
+        self.conn.prepare_cached("INSERT OR IGNORE INTO cases (case_key, subset, name) VALUES (?, ?, ?)")?
+            .execute(&[&record.case_key as &ToSql, &subset(alg), &record.case_name])?;
+        // a name for a case that was added without one
+        if record.case_name.is_some() {
+            self.conn.prepare_cached("UPDATE cases SET name = ? WHERE case_key = ? AND name IS NULL")?
+                .execute(&[&record.case_name as &ToSql, &record.case_key])?;
+        }
+        let id = self.conn.prepare_cached("SELECT id FROM cases WHERE case_key = ?")?
+            .query_row(&[&record.case_key], |row| row.get(0))?;
+        self.case_ids.insert(record.case_key.clone(), id);
+        Ok(id)
+    }
+
+    pub fn add(&mut self, alg: &Algorithm, names: &CaseNames) -> Result<(), Box<Error>> {
+        self.add_record(alg, &AlgRecord::new(alg, names))
+    }
+
+    // Adds an alg that came from enumerating them in order, so that a later export can carry on
+    // after it.
+    pub fn add_enumerated(&mut self, alg: &Algorithm, names: &CaseNames) -> Result<(), Box<Error>> {
+        self.add(alg, names)?;
+        self.enumerated_to = Some(format!("{}", alg));
+        Ok(())
+    }
+
+    // Adds the alg as `record` describes it, e.g. with a name for the case from elsewhere.
+    pub fn add_record(&mut self, alg: &Algorithm, record: &AlgRecord) -> Result<(), Box<Error>> {
+        if self.in_batch == BATCH_SIZE {
+            self.commit()?;
+        }
+        if self.in_batch == 0 {
+            self.conn.execute_batch("BEGIN")?;
+        }
+        let case_id = self.case_id(alg, record)?;
+        let state = record.state.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(" ");
+        self.conn.prepare_cached("INSERT OR IGNORE INTO algs (alg, htm, qtm, stm, canonical, inverse, state)
+                                  VALUES (?, ?, ?, ?, ?, ?, ?)")?
+            .execute(&[&record.alg as &ToSql, &(record.htm as i64), &(record.qtm as i64), &(record.stm as i64),
+                       &record.canonical, &record.inverse, &state])?;
+        let alg_id: i64 = self.conn.prepare_cached("SELECT id FROM algs WHERE alg = ?")?
+            .query_row(&[&record.alg], |row| row.get(0))?;
+        self.conn.prepare_cached("INSERT OR IGNORE INTO alg_cases (alg_id, case_id) VALUES (?, ?)")?
+            .execute(&[&alg_id, &case_id])?;
+        self.in_batch += 1;
+        Ok(())
+    }
+
+    // Saves whatever's been added so far.
+    pub fn commit(&mut self) -> Result<(), Box<Error>> {
+        if self.in_batch > 0 {
+            if let Some(alg) = self.enumerated_to.take() {
+                self.conn.prepare_cached("INSERT OR REPLACE INTO progress (name, alg) VALUES ('export', ?)")?
+                    .execute(&[&alg])?;
+            }
+            self.conn.execute_batch("COMMIT")?;
+            self.in_batch = 0;
+        }
+        Ok(())
+    }
+
+    // The last enumerated alg saved, which is where to carry on enumerating from.
+    pub fn enumerated_to(&self) -> Result<Option<Algorithm>, Box<Error>> {
+        let mut statement = self.conn.prepare("SELECT alg FROM progress WHERE name = 'export'")?;
+        let mut algs = statement.query_map(NO_PARAMS, |row| row.get::<usize, String>(0))?;
+        match algs.next() {
+            Some(alg) => Ok(Some(Algorithm::from_str(alg?.as_str())?)),
+            None => Ok(None),
+        }
+    }
+
+    // How many algs and cases there are.
+    pub fn counts(&self) -> Result<(i64, i64), Box<Error>> {
+        let algs = self.conn.query_row("SELECT COUNT(*) FROM algs", NO_PARAMS, |row| row.get(0))?;
+        let cases = self.conn.query_row("SELECT COUNT(*) FROM cases", NO_PARAMS, |row| row.get(0))?;
+        Ok((algs, cases))
+    }
+
+    // Every alg for the case with the given key no longer than `max_htm`, shortest first.
+    pub fn algs_for_case(&self, case_key: &str, max_htm: usize) -> Result<Vec<String>, Box<Error>> {
+        let mut statement = self.conn.prepare(
+            "SELECT algs.alg FROM algs
+               JOIN alg_cases ON alg_cases.alg_id = algs.id
+               JOIN cases ON cases.id = alg_cases.case_id
+             WHERE cases.case_key = ? AND algs.htm <= ?
+             ORDER BY algs.htm, algs.id")?;
+        let algs = statement.query_map(&[&case_key as &ToSql, &(max_htm as i64)], |row| row.get(0))?;
+        let mut result = vec![];
+        for alg in algs {
+            result.push(alg?);
+        }
+        Ok(result)
+    }
+}
+
+impl Drop for AlgDatabase {
+    fn drop(&mut self) {
+        let _ = self.commit();
+    }
+}
+
+#[test]
+fn stores_algs_by_case() {
+    use cases::case_key;
+    let names = CaseNames::parse("Sune,R U R' U R U2 R'").unwrap();
+    let sune = Algorithm::from_str("R U R' U R U2 R'").unwrap();
+    let mut db = AlgDatabase::open(":memory:").unwrap();
+    for alg in ["R U R' U R U2 R'", "R U2 R' U' R U' R'", "U L U L' U L U2 L'", "R U R' U R U2 R'"].iter() {
+        db.add(&Algorithm::from_str(alg).unwrap(), &names).unwrap();
+    }
+    db.add(&Algorithm::from_str("U R U R' U R U2 R' U R' F R2 U' R' U' R U R' F'").unwrap(), &names).unwrap();
+    db.commit().unwrap();
+
+    assert_eq!(db.counts().unwrap(), (4, 3));
+    assert_eq!(db.algs_for_case(case_key(&sune).as_str(), 14).unwrap(), vec!["R U R' U R U2 R'", "U L U L' U L U2 L'"]);
+    assert_eq!(db.algs_for_case(case_key(&sune).as_str(), 7).unwrap(), vec!["R U R' U R U2 R'"]);
+    let (subset, name): (String, Option<String>) = db.conn.query_row(
+        "SELECT subset, name FROM cases WHERE case_key = ?", &[&case_key(&sune)], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+    assert_eq!((subset.as_str(), name), ("ZBLL", Some(String::from("Sune"))));
+}
+
+#[test]
+fn carries_on_from_the_last_enumerated_alg() {
+    use algorithm_iterator::AlgorithmIterator;
+    let names = CaseNames::parse("").unwrap();
+    let enumerated: Vec<Algorithm> = AlgorithmIterator::from_starting_algorithm("").unwrap().take(5).collect();
+    let mut db = AlgDatabase::open(":memory:").unwrap();
+    assert!(db.enumerated_to().unwrap().is_none());
+
+    for alg in &enumerated[..3] {
+        db.add_enumerated(alg, &names).unwrap();
+    }
+    // an import in between, of an alg that comes well after those
+    db.add(&Algorithm::from_str("U R U R' U R U2 R' U R' F R2 U' R' U' R U R' F'").unwrap(), &names).unwrap();
+    db.commit().unwrap();
+    assert_eq!(format!("{}", db.enumerated_to().unwrap().unwrap()), format!("{}", enumerated[2]));
+
+    for alg in &enumerated[3..] {
+        db.add_enumerated(alg, &names).unwrap();
+    }
+    db.add(&Algorithm::from_str("R U2 R' U' R U' R'").unwrap(), &names).unwrap();
+    db.commit().unwrap();
+    assert_eq!(format!("{}", db.enumerated_to().unwrap().unwrap()), format!("{}", enumerated[4]));
+    assert_eq!(db.counts().unwrap().0, 7);
+}
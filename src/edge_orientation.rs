@@ -148,6 +148,27 @@ impl Prunable for Orientation {
     fn total_states() -> usize { NUM_ORIENTATIONS }
 }
 
+// Where a move takes the edge at `position`, and whether it flips it on the way.
+pub fn move_edge(idx: usize, position: usize) -> (usize, u8) {
+    let m = &MOVES_BY_INDEX[idx];
+    let to = m.positions.iter().position(|&p| p == position).unwrap();
+    (to, m.orientation_effect[position])
+}
+
+// Whether the edge at `position` is flipped in the orientation with the given index. The last
+// edge's isn't in the index, as there are always an even number flipped.
+pub fn flipped(eo: EOIndex, position: usize) -> u8 {
+    if position < 11 {
+        ((eo >> (10 - position)) & 1) as u8
+    } else {
+        (eo.count_ones() % 2) as u8
+    }
+}
+
+pub fn make_tables() -> (Vec<[usize; 18]>, Vec<u16>) {
+    (Orientation::make_transition_table(), Orientation::make_pruning_table())
+}
+
 lazy_static! {
     pub static ref EO_TRANSITIONS: Vec<[usize; 18]> = {
         Orientation::make_transition_table()
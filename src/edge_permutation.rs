@@ -49,6 +49,11 @@ impl EdgePermutation {
         true
     }
 
+    // The edge at a position, numbered by the position it's solved in.
+    pub fn piece_at(&self, position: usize) -> usize {
+        self.state[position] as usize
+    }
+
     pub fn apply_idx(&self, idx: usize) -> Self {
         let mut dest = EdgePermutation::new();
         self.apply_into_idx(idx, &mut dest);
@@ -1,7 +1,7 @@
 use generator::{Generator, GENERATORS};
 use corner_permutation::{CPIndex, CP_SOLVED, TRANSITIONS, PRUNING};
 use corner_orientation::{COIndex, CO_SOLVED, CO_TRANSITIONS, CO_PRUNING};
-use edge_orientation::{EOIndex, EO_SOLVED, EO_TRANSITIONS, EO_PRUNING};
+use edge_orientation::{EOIndex, EO_SOLVED, EO_TRANSITIONS, EO_PRUNING, flipped};
 use edge_permutation::EdgePermutation;
 use generator::move_indices::{F, R, U, RPRIME, UPRIME, FPRIME, D};
 use cubestate::CubeState;
@@ -54,15 +54,35 @@ impl F2LCubeState {
             && self.edgeperm.is_solved()
     }
 
-    pub fn is_ll(&self) -> bool {
+    // Whether everything but the last layer is solved.
+    pub fn f2l_solved(&self) -> bool {
         PRUNING[self.cornerperm] == 0
             && CO_PRUNING[self.cornerorie] == 0
             && EO_PRUNING[self.edgeorie] == 0
             && self.edgeperm.is_ll()
-            && !(self.is_id()
-                 || self.apply_idx(0).is_id()
-                 || self.apply_idx(1).is_id()
-                 || self.apply_idx(2).is_id())
+    }
+
+    // Whether the cube's solved, or would be after a U turn.
+    pub fn solved_up_to_auf(&self) -> bool {
+        self.f2l_solved()
+            && (self.is_id()
+                || self.apply_idx(0).is_id()
+                || self.apply_idx(1).is_id()
+                || self.apply_idx(2).is_id())
+    }
+
+    pub fn is_ll(&self) -> bool {
+        self.f2l_solved() && !self.solved_up_to_auf()
+    }
+
+    // The corners' permutation and orientation, as indices into their tables.
+    pub fn corners(&self) -> (CPIndex, COIndex) {
+        (self.cornerperm, self.cornerorie)
+    }
+
+    // The edge at a position, and whether it's flipped.
+    pub fn edge_at(&self, position: usize) -> (usize, u8) {
+        (self.edgeperm.piece_at(position), flipped(self.edgeorie, position))
     }
 
     pub fn prunable(&self, dist: u16) -> bool {
@@ -95,4 +115,6 @@ fn is_ll_is_false_if_ll_is_solved() {
 
     let result = state.apply_idx(U);
     assert!(!result.is_ll());
+    assert!(result.f2l_solved());
+    assert!(!state.apply_idx(D).f2l_solved());
 }
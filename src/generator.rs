@@ -38,6 +38,15 @@ impl Face {
         }
     }
 
+    // Reflects through the plane between R and L.
+    fn mirror(&self) -> Face {
+        match self {
+            &Face::R => Face::L,
+            &Face::L => Face::R,
+            &f => f,
+        }
+    }
+
     fn axis(&self) -> Axis {
         match self {
             &Face::U => Axis::UD,
@@ -216,6 +225,11 @@ impl Generator {
         Self::from_face_and_modifier(self.face.rotate_y(), self.modifier)
     }
 
+    // Mirroring turns every move the other way, as well as swapping R and L.
+    pub fn mirror(&self) -> Self {
+        Self::from_face_and_modifier(self.face.mirror(), self.modifier.inverse())
+    }
+
     fn from_face_and_modifier(f: Face, m: Modifier) -> Self {
         GENERATORS[f as usize * 3 + m as usize]
     }
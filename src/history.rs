@@ -0,0 +1,103 @@
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use rustc_serialize::json;
+use algorithm::Algorithm;
+use cases::{case_key, symmetry_keys};
+
+// Everything the bot has ever posted, one JSON object a line, only ever added to. It's what lets
+// it avoid posting a case it posted recently.
+
+#[derive(RustcEncodable, RustcDecodable, Clone, Debug, PartialEq)]
+pub struct PostedTo {
+    pub backend: String,
+    pub post_id: String,
+}
+
+#[derive(RustcEncodable, RustcDecodable, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    // seconds since the epoch
+    pub time: u64,
+    pub alg: String,
+    pub case: String,
+    pub posts: Vec<PostedTo>,
+}
+
+pub struct History {
+    path: PathBuf,
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    pub fn load(filename: &str) -> Result<Self, Box<Error>> {
+        let path = Path::new(filename);
+        let mut entries = vec![];
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    entries.push(json::decode(line.as_str())?);
+                }
+            }
+        }
+        Ok(History { path: path.to_path_buf(), entries: entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    // Whether the alg solves one of the cases of the last `recent` posts, or a mirror or inverse
+    // of one.
+    pub fn posted_recently(&self, alg: &Algorithm, recent: usize) -> bool {
+        let keys = symmetry_keys(alg);
+        self.entries.iter().rev().take(recent).any(|e| keys.contains(&e.case))
+    }
+
+    pub fn record(&mut self, alg: &Algorithm, posts: Vec<PostedTo>) -> Result<(), Box<Error>> {
+        let case = case_key(alg);
+        let alg = format!("{}", alg);
+        // a run that died after writing this but before marking the alg as posted will try to
+        // write it again
+        if self.entries.last().map(|e| e.alg == alg).unwrap_or(false) {
+            return Ok(());
+        }
+        let entry = HistoryEntry {
+            time: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            case: case,
+            alg: alg,
+            posts: posts,
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(format!("{}\n", json::encode(&entry)?).as_bytes())?;
+        file.sync_all()?;
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+#[test]
+fn avoids_recent_cases() {
+    use std::str::FromStr;
+    let filename = ::std::env::temp_dir().join(format!("lla-history-{}.jsonl", ::std::process::id()));
+    let filename = filename.to_str().unwrap();
+    let sune = Algorithm::from_str("R U R' U R U2 R'").unwrap();
+    let t_perm = Algorithm::from_str("R U R' U' R' F R2 U' R' U' R U R' F'").unwrap();
+    let antisune = Algorithm::from_str("R U2 R' U' R U' R'").unwrap();
+
+    let mut history = History::load(filename).unwrap();
+    history.record(&sune, vec![PostedTo { backend: String::from("twitter"), post_id: String::from("1") }]).unwrap();
+    history.record(&sune, vec![]).unwrap();
+    history.record(&t_perm, vec![]).unwrap();
+
+    let history = History::load(filename).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history.entries[0].posts[0].post_id, "1");
+    assert!(history.posted_recently(&antisune, 2));
+    assert!(!history.posted_recently(&antisune, 1));
+    assert!(!history.posted_recently(&antisune, 0));
+
+    ::std::fs::remove_file(filename).unwrap();
+}
@@ -0,0 +1,381 @@
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use curl;
+use curl::easy::{Easy, List};
+use rand::{self, Rng};
+use rustc_serialize::json::Json;
+use lla_error::LLAError;
+use schedule::days_from_civil;
+
+// Just enough HTTP for the posting backends: one request in, status, headers and body out.
+
+pub struct Request {
+    pub method: &'static str,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    // Whether it does no harm if the server ends up acting on this twice, which decides whether
+    // it can be tried again after a failure that might have happened once it got there.
+    pub repeatable: bool,
+    // Makes a header that has to be fresh every time the request goes out, like an OAuth
+    // signature, which the server won't take twice.
+    signer: Option<Signer>,
+}
+
+// Gives back a header's name and value.
+type Signer = Box<Fn(&Request) -> (String, String)>;
+
+impl Request {
+    pub fn new(method: &'static str, url: &str) -> Self {
+        Request {
+            method: method,
+            url: String::from(url),
+            headers: vec![],
+            body: vec![],
+            repeatable: method != "POST",
+            signer: None,
+        }
+    }
+
+    // Marks a POST as safe to send again, e.g. an upload, where a repeat only leaves an unused
+    // copy behind.
+    pub fn repeatable(mut self) -> Self {
+        self.repeatable = true;
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+
+    // Adds the header `signer` makes, made again before each try.
+    pub fn signed_by<F: Fn(&Request) -> (String, String) + 'static>(mut self, signer: F) -> Self {
+        self.signer = Some(Box::new(signer));
+        self
+    }
+
+    pub fn json(self, body: &Json) -> Self {
+        let mut req = self.header("Content-Type", "application/json");
+        req.body = format!("{}", body).into_bytes();
+        req
+    }
+
+    pub fn multipart(self, form: Multipart) -> Self {
+        let mut req = self.header("Content-Type", format!("multipart/form-data; boundary={}", form.boundary).as_str());
+        req.body = form.finish();
+        req
+    }
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u32,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(&self) -> Result<Json, Box<Error>> {
+        Ok(Json::from_str(String::from_utf8(self.body.clone())?.as_str())?)
+    }
+
+    // Pulls a string (or number, some APIs send ids as those) out of a JSON response body.
+    pub fn field(&self, path: &[&str]) -> Result<String, Box<Error>> {
+        let json = self.json()?;
+        match json.find_path(path) {
+            Some(&Json::String(ref s)) => Ok(s.clone()),
+            Some(&Json::U64(n)) => Ok(format!("{}", n)),
+            Some(&Json::I64(n)) => Ok(format!("{}", n)),
+            _ => Err(From::from(format!("No {} in response: {}", path.join("."), json))),
+        }
+    }
+}
+
+// How hard to try before giving up on a request.
+pub struct Backoff {
+    pub attempts: u32,
+    // seconds before the first retry, doubling each time after
+    pub first_wait: u64,
+    // the longest we'll wait for a rate limit to reset, rather than give up until the next run
+    pub max_wait: u64,
+}
+
+pub const BACKOFF: Backoff = Backoff { attempts: 5, first_wait: 2, max_wait: 15 * 60 };
+
+// Sends the request, giving back an error for anything but a 2xx. Failures that might go away are
+// tried again, so long as that can't end up doing something twice.
+pub fn send(req: &Request) -> Result<Response, Box<Error>> {
+    send_with(req, &BACKOFF, &mut |secs| thread::sleep(Duration::from_secs(secs)))
+}
+
+pub fn send_with(req: &Request, backoff: &Backoff, sleep: &mut FnMut(u64)) -> Result<Response, Box<Error>> {
+    let mut wait = backoff.first_wait;
+    let mut attempt = 1;
+    loop {
+        let failure = match send_once(req) {
+            Ok(response) => return Ok(response),
+            Err(failure) => failure,
+        };
+        let retry_in = match failure.error.downcast_ref::<LLAError>() {
+            Some(&LLAError::Transient(_)) if req.repeatable || !failure.maybe_received => Some(wait),
+            // the server turned it down, so it's always safe to send again
+            Some(&LLAError::RateLimited(_, reset)) => Some(reset.unwrap_or(wait)).filter(|&secs| secs <= backoff.max_wait),
+            _ => None,
+        };
+        match retry_in {
+            Some(secs) if attempt < backoff.attempts => {
+                eprintln!("{} {}: {}, trying again in {}s", req.method, req.url, failure.error, secs);
+                sleep(secs);
+            },
+            _ => return Err(failure.error),
+        }
+        attempt += 1;
+        wait *= 2;
+    }
+}
+
+// Why a request didn't work, and whether the server might have acted on it anyway.
+struct Failure {
+    error: Box<Error>,
+    maybe_received: bool,
+}
+
+fn send_once(req: &Request) -> Result<Response, Failure> {
+    let response = perform(req).map_err(|err| {
+        let not_sent = err.is_couldnt_resolve_host() || err.is_couldnt_resolve_proxy() || err.is_couldnt_connect();
+        let dropped = err.is_operation_timedout() || err.is_send_error() || err.is_recv_error() || err.is_got_nothing();
+        let message = format!("{} {}: {}", req.method, req.url, err);
+        Failure {
+            error: if not_sent || dropped { From::from(LLAError::Transient(message)) } else { From::from(message) },
+            maybe_received: !not_sent,
+        }
+    })?;
+    if response.status >= 200 && response.status < 300 {
+        return Ok(response);
+    }
+
+    let message = format!("{} {} gave {}: {}", req.method, req.url, response.status, String::from_utf8_lossy(&response.body));
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (error, maybe_received): (Box<Error>, bool) = match response.status {
+        401 | 403 => (From::from(LLAError::Auth(message)), false),
+        429 => (From::from(LLAError::RateLimited(message, rate_limit_wait(&response.headers, now))), false),
+        408 | 500...599 => (From::from(LLAError::Transient(message)), true),
+        _ => (From::from(message), true),
+    };
+    Err(Failure { error: error, maybe_received: maybe_received })
+}
+
+fn perform(req: &Request) -> Result<Response, curl::Error> {
+    let mut easy = Easy::new();
+    easy.url(req.url.as_str())?;
+    match req.method {
+        "GET" => easy.get(true)?,
+        m => {
+            // anything else with a body gets sent like a POST would be, just under its own name
+            easy.post(true)?;
+            easy.post_fields_copy(&req.body)?;
+            if m != "POST" {
+                easy.custom_request(m)?;
+            }
+        },
+    }
+    let mut headers = List::new();
+    let signed = req.signer.as_ref().map(|sign| sign(req));
+    for &(ref name, ref value) in req.headers.iter().chain(signed.iter()) {
+        headers.append(format!("{}: {}", name, value).as_str())?;
+    }
+    easy.http_headers(headers)?;
+
+    let mut body = vec![];
+    let mut response_headers = vec![];
+    {
+        let mut transfer = easy.transfer();
+        transfer.write_function(|data| {
+            body.extend_from_slice(data);
+            Ok(data.len())
+        })?;
+        transfer.header_function(|line| {
+            let line = String::from_utf8_lossy(line);
+            if let Some(idx) = line.find(':') {
+                response_headers.push((line[..idx].trim().to_lowercase(), String::from(line[idx + 1..].trim())));
+            }
+            true
+        })?;
+        transfer.perform()?;
+    }
+    Ok(Response { status: easy.response_code()?, headers: response_headers, body: body })
+}
+
+// Seconds since the epoch for a UTC time like "2017-05-13T18:00:00.000Z".
+fn parse_utc(s: &str) -> Option<u64> {
+    if s.len() < 20 || !s.ends_with('Z') {
+        return None;
+    }
+    let field = |from: usize, to: usize| s.get(from..to).and_then(|f| f.parse::<i64>().ok());
+    let (y, m, d) = (field(0, 4)?, field(5, 7)?, field(8, 10)?);
+    let (h, min, sec) = (field(11, 13)?, field(14, 16)?, field(17, 19)?);
+    let secs = days_from_civil(y, m as u32, d as u32) * 86400 + h * 3600 + min * 60 + sec;
+    if secs < 0 { None } else { Some(secs as u64) }
+}
+
+// How many seconds until a rate limit resets, going by whichever header the server sent:
+// `Retry-After` in seconds, or a reset time, which Twitter gives as a Unix time and Mastodon as
+// an ISO 8601 one.
+fn rate_limit_wait(headers: &[(String, String)], now: u64) -> Option<u64> {
+    let header = |name: &str| headers.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref v)| v.as_str());
+    if let Some(secs) = header("retry-after").and_then(|v| v.parse().ok()) {
+        return Some(secs);
+    }
+    let reset = header("x-rate-limit-reset").or(header("x-ratelimit-reset"))?;
+    let reset = reset.parse().ok().or_else(|| parse_utc(reset))?;
+    // a second's grace, in case our clocks don't quite agree
+    Some(reset.saturating_sub(now) + 1)
+}
+
+pub fn content_type(filename: &str) -> &'static str {
+    if filename.ends_with(".gif") {
+        "image/gif"
+    } else if filename.ends_with(".png") {
+        "image/png"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+// A multipart/form-data body, for uploading files.
+pub struct Multipart {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl Multipart {
+    pub fn new() -> Self {
+        let suffix: String = rand::thread_rng().gen_ascii_chars().take(24).collect();
+        Self::with_boundary(format!("------------------------{}", suffix).as_str())
+    }
+
+    fn with_boundary(boundary: &str) -> Self {
+        Multipart { boundary: String::from(boundary), body: vec![] }
+    }
+
+    pub fn text(mut self, name: &str, value: &str) -> Self {
+        self.body.extend(format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", self.boundary, name, value).bytes());
+        self
+    }
+
+    pub fn file(mut self, name: &str, filename: &str, data: &[u8]) -> Self {
+        self.body.extend(format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                                 self.boundary, name, filename, content_type(filename)).bytes());
+        self.body.extend_from_slice(data);
+        self.body.extend(b"\r\n".iter());
+        self
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.body.extend(format!("--{}--\r\n", self.boundary).bytes());
+        self.body
+    }
+}
+
+#[test]
+fn builds_multipart_body() {
+    let body = Multipart::with_boundary("xyz")
+        .text("description", "a case")
+        .file("file", "case.png", b"PNG")
+        .finish();
+    assert_eq!(String::from_utf8(body).unwrap(),
+               "--xyz\r\nContent-Disposition: form-data; name=\"description\"\r\n\r\na case\r\n\
+                --xyz\r\nContent-Disposition: form-data; name=\"file\"; filename=\"case.png\"\r\nContent-Type: image/png\r\n\r\nPNG\r\n\
+                --xyz--\r\n");
+}
+
+#[cfg(test)]
+const NO_WAITING: Backoff = Backoff { attempts: 3, first_wait: 1, max_wait: 60 };
+
+#[test]
+fn retries_only_what_is_safe_to_retry() {
+    use mock_server::MockServer;
+    let mut sleeps = vec![];
+
+    let server = MockServer::start(vec![(503, "{}"), (200, r#"{"id": "1"}"#)]);
+    let response = send_with(&Request::new("POST", server.url.as_str()).repeatable(), &NO_WAITING, &mut |s| sleeps.push(s)).unwrap();
+    assert_eq!(response.field(&["id"]).unwrap(), "1");
+    assert_eq!(server.requests().len(), 2);
+    assert_eq!(sleeps, vec![1]);
+
+    // it might have been posted before the server fell over
+    let server = MockServer::start(vec![(503, "{}")]);
+    let err = send_with(&Request::new("POST", server.url.as_str()), &NO_WAITING, &mut |s| sleeps.push(s)).unwrap_err();
+    assert!(match err.downcast_ref::<LLAError>() { Some(&LLAError::Transient(_)) => true, _ => false });
+    assert_eq!(server.requests().len(), 1);
+
+    let server = MockServer::start(vec![(401, r#"{"error": "bad token"}"#)]);
+    let err = send_with(&Request::new("GET", server.url.as_str()), &NO_WAITING, &mut |s| sleeps.push(s)).unwrap_err();
+    assert!(match err.downcast_ref::<LLAError>() { Some(&LLAError::Auth(_)) => true, _ => false });
+    assert_eq!(server.requests().len(), 1);
+    assert_eq!(sleeps, vec![1]);
+}
+
+#[test]
+fn signs_each_try_afresh() {
+    use std::cell::Cell;
+    use mock_server::MockServer;
+    let server = MockServer::start(vec![(503, "{}"), (200, "{}")]);
+    let tries = ::std::rc::Rc::new(Cell::new(0));
+    let counter = tries.clone();
+    let req = Request::new("POST", server.url.as_str()).repeatable().signed_by(move |_| {
+        counter.set(counter.get() + 1);
+        (String::from("Authorization"), format!("try {}", counter.get()))
+    });
+    send_with(&req, &NO_WAITING, &mut |_| ()).unwrap();
+    let requests = server.requests();
+    assert_eq!(requests[0].header("authorization"), Some("try 1"));
+    assert_eq!(requests[1].header("authorization"), Some("try 2"));
+    assert_eq!(tries.get(), 2);
+}
+
+#[test]
+fn waits_out_rate_limits() {
+    use mock_server::MockServer;
+    let mut sleeps = vec![];
+    let server = MockServer::start_with_headers(vec![
+        (429, vec![("Retry-After", "7")], "{}"),
+        (429, vec![], "{}"),
+        (200, vec![], r#"{"id": "1"}"#),
+    ]);
+    send_with(&Request::new("POST", server.url.as_str()), &NO_WAITING, &mut |s| sleeps.push(s)).unwrap();
+    assert_eq!(server.requests().len(), 3);
+    assert_eq!(sleeps, vec![7, 2]);
+
+    // not worth hanging around for
+    let server = MockServer::start_with_headers(vec![(429, vec![("Retry-After", "3600")], "{}")]);
+    let err = send_with(&Request::new("POST", server.url.as_str()), &NO_WAITING, &mut |s| sleeps.push(s)).unwrap_err();
+    assert!(match err.downcast_ref::<LLAError>() { Some(&LLAError::RateLimited(_, Some(3600))) => true, _ => false });
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn retries_when_nothing_was_sent() {
+    let url = {
+        let listener = ::std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    };
+    let mut sleeps = vec![];
+    let err = send_with(&Request::new("POST", url.as_str()), &NO_WAITING, &mut |s| sleeps.push(s)).unwrap_err();
+    assert!(match err.downcast_ref::<LLAError>() { Some(&LLAError::Transient(_)) => true, _ => false });
+    assert_eq!(sleeps, vec![1, 2]);
+}
+
+#[test]
+fn reads_rate_limit_resets() {
+    let headers = |name: &str, value: &str| vec![(String::from(name), String::from(value))];
+    let now = 1_500_000_000;
+    assert_eq!(rate_limit_wait(&headers("retry-after", "30"), now), Some(30));
+    assert_eq!(rate_limit_wait(&headers("x-rate-limit-reset", "1500000100"), now), Some(101));
+    assert_eq!(parse_utc("2017-07-14T02:40:00.000Z"), Some(1_500_000_000));
+    assert_eq!(rate_limit_wait(&headers("x-ratelimit-reset", "2017-07-14T02:45:00.000Z"), now), Some(301));
+    assert_eq!(rate_limit_wait(&headers("x-ratelimit-reset", "soon"), now), None);
+    assert_eq!(rate_limit_wait(&[], now), None);
+}
@@ -1,7 +1,11 @@
 use cairo::surface::Surface;
 use cairo::Cairo;
 use cairo::surface::format::Format;
-use cubestate::CubeState;
+use cubestate::{CubeState, CORNER_SLOTS, EDGE_SLOTS};
+use generator::Face;
+use image_style::{sticker_face, ImageStyle, Rgb};
+use lla_error::LLAError;
+use std::str::FromStr;
 
 // This module is pretty messy and I'd like to come up with a cleaner way to structure this image
 // drawing code, but I think manipulating this kind of thing is just often gross...
@@ -11,8 +15,8 @@ const HEIGHT: i32 = 250;
 const STICKER_SIZE: f64 = 40.;
 const HALF_STICKER_SIZE: f64 = STICKER_SIZE / 2.;
 
-const FWIDTH: f64 = WIDTH as f64;
-const FHEIGHT: f64 = HEIGHT as f64;
+pub const FWIDTH: f64 = WIDTH as f64;
+pub const FHEIGHT: f64 = HEIGHT as f64;
 const VANISH_AMOUNT: f64 = 200.;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -91,19 +95,28 @@ fn project_to((x1, y1): (f64, f64), (x2, y2): (f64, f64), side: Side, len: f64)
     (x, y)
 }
 
-fn draw_sticker(cr: &mut ::cairo::Cairo, s: &Sticker, (r, g, b): (f64, f64, f64)) {
+// Fills in the polygon with the given colour, and outlines it in black.
+pub fn draw_polygon(cr: &mut ::cairo::Cairo, points: &[(f64, f64)], (r, g, b): Rgb) {
+    cr.new_path();
+    for &(x, y) in points.iter() {
+        cr.line_to(x, y);
+    }
+    cr.close_path();
+    cr.set_source_rgba(r, g, b, 1.);
+    cr.fill_preserve();
+    cr.set_source_rgba(0., 0., 0., 1.);
+    cr.stroke();
+}
+
+fn draw_sticker(cr: &mut ::cairo::Cairo, s: &Sticker, rgb: Rgb) {
     match s {
         &Sticker::Square { x, y } => {
-            cr.new_path();
-            cr.line_to(x - HALF_STICKER_SIZE, y - HALF_STICKER_SIZE);
-            cr.line_to(x - HALF_STICKER_SIZE, y + HALF_STICKER_SIZE);
-            cr.line_to(x + HALF_STICKER_SIZE, y + HALF_STICKER_SIZE);
-            cr.line_to(x + HALF_STICKER_SIZE, y - HALF_STICKER_SIZE);
-            cr.close_path();
-            cr.set_source_rgba(r, g, b, 1.);
-            cr.fill_preserve();
-            cr.set_source_rgba(0., 0., 0., 1.);
-            cr.stroke();
+            draw_polygon(cr, &[
+                (x - HALF_STICKER_SIZE, y - HALF_STICKER_SIZE),
+                (x - HALF_STICKER_SIZE, y + HALF_STICKER_SIZE),
+                (x + HALF_STICKER_SIZE, y + HALF_STICKER_SIZE),
+                (x + HALF_STICKER_SIZE, y - HALF_STICKER_SIZE),
+            ], rgb);
         },
         &Sticker::Vanishing { x, y, vanish_side: ref side } => {
             let (offx, offy) = side.offset();
@@ -114,62 +127,187 @@ fn draw_sticker(cr: &mut ::cairo::Cairo, s: &Sticker, (r, g, b): (f64, f64, f64)
             let p1 = project_to(p2, vanish, *side, VANISH_STICKER_LEN);
             let p4 = project_to(p3, vanish, *side, VANISH_STICKER_LEN);
 
-            let (p1x, p1y) = p1;
-            let (p2x, p2y) = p2;
-            let (p3x, p3y) = p3;
-            let (p4x, p4y) = p4;
-
-            cr.new_path();
-            cr.line_to(p1x, p1y);
-            cr.line_to(p2x, p2y);
-            cr.line_to(p3x, p3y);
-            cr.line_to(p4x, p4y);
-            cr.close_path();
-            cr.set_source_rgba(r, g, b, 1.);
-            cr.fill_preserve();
-            cr.set_source_rgba(0., 0., 0., 1.);
-            cr.stroke();
+            draw_polygon(cr, &[p1, p2, p3, p4], rgb);
         }
     }
 }
 
-enum Color {
-    Yellow,
-    White,
-    Green,
-    Blue,
-    Orange,
-    Red,
+// Paints the background and sets things up so that a drawing laid out for a `width` x `height`
+// image gets scaled to fit the style's size, centred. The surface should be `style.image_size`.
+pub fn set_up_canvas(cr: &mut ::cairo::Cairo, style: &ImageStyle, width: f64, height: f64) {
+    if let Some((r, g, b)) = style.background {
+        cr.set_source_rgba(r, g, b, 1.);
+        cr.paint();
+    }
+
+    let (image_width, image_height) = style.image_size(width, height);
+    let (image_width, image_height) = (image_width as f64, image_height as f64);
+    let scale = (image_width / width).min(image_height / height);
+    cr.translate((image_width - width * scale) / 2., (image_height - height * scale) / 2.);
+    cr.scale(scale, scale);
+
+    cr.set_line_width(style.line_width);
 }
 
-fn sticker_color(i: u8) -> Color {
-    match i {
-        0  | 1  | 2  | 3  | 4  | 5  | 6  | 7  | 8  => Color::Yellow,
-        9  | 10 | 11 | 18 | 19 | 20 | 27 | 28 | 29 => Color::Red,
-        12 | 13 | 14 | 21 | 22 | 23 | 30 | 31 | 32 => Color::Green,
-        15 | 16 | 17 | 24 | 25 | 26 | 33 | 34 | 35 => Color::Orange,
-        36 | 37 | 38 | 39 | 40 | 41 | 42 | 43 | 44 => Color::White,
-        45 | 46 | 47 | 48 | 49 | 50 | 51 | 52 | 53 => Color::Blue,
-        _ => panic!("Don't have a color for {}", i)
+const SQUARE_SPACING: f64 = 4.;
+
+const ARROW_LINE_WIDTH: f64 = 3.;
+const ARROW_HEAD_LENGTH: f64 = 12.;
+const ARROW_HEAD_WIDTH: f64 = 7.;
+// How far short of the sticker centres an arrow stops, so that arrows meeting at the same
+// sticker don't run into each other.
+const ARROW_MARGIN: f64 = STICKER_SIZE * 0.3;
+
+// Which stickers a diagram shows the colour of, for drilling a particular step. The rest are
+// drawn grey.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stickering {
+    // Everything
+    Full,
+    // Only the U coloured stickers
+    OLL,
+    // All of the last layer's corners, but only the U coloured edge stickers
+    COLL,
+    // Only the last layer. The top view only has last layer stickers, so this looks the same as
+    // `Full` there.
+    ZBLL,
+}
+
+impl Default for Stickering {
+    fn default() -> Self {
+        Stickering::Full
+    }
+}
+
+impl FromStr for Stickering {
+    type Err = LLAError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Stickering::Full),
+            "oll"  => Ok(Stickering::OLL),
+            "coll" => Ok(Stickering::COLL),
+            "zbll" => Ok(Stickering::ZBLL),
+            &_ => Err(LLAError::InvalidOption(format!("No stickering '{}'", s))),
+        }
     }
 }
 
-impl Color {
-    fn rgb(&self) -> (f64, f64, f64) {
+fn in_slots(slots: &[&[usize]], i: usize) -> bool {
+    slots.iter().any(|slot| slot.contains(&i))
+}
+
+impl Stickering {
+    // Whether the sticker which started out at `sticker` is shown, wherever it is now. It goes by
+    // the piece the sticker belongs to, so the same stickers are shown however the pieces are
+    // moved about.
+    pub fn shows(&self, sticker: u8) -> bool {
+        let is_u_sticker = sticker < 9;
+        let sticker = sticker as usize;
         match self {
-            &Color::Yellow => (1.,  1., 0.),
-            &Color::White  => (1.,  1., 1.),
-            &Color::Green  => (0.,  1., 0.),
-            &Color::Blue   => (0.,  0., 1.),
-            &Color::Red    => (1.,  0., 0.),
-            &Color::Orange => (1., 0.5, 0.),
+            &Stickering::Full => true,
+            &Stickering::OLL => is_u_sticker,
+            &Stickering::COLL => is_u_sticker || in_slots(&CORNER_SLOTS, sticker),
+            &Stickering::ZBLL => is_u_sticker || in_slots(&CORNER_SLOTS, sticker) || in_slots(&EDGE_SLOTS, sticker),
+        }
+    }
+
+    // The face whose colour to draw the sticker with, or `None` if it's hidden.
+    pub fn face(&self, sticker: u8) -> Option<Face> {
+        if self.shows(sticker) {
+            Some(sticker_face(sticker))
+        } else {
+            None
         }
     }
 }
 
-const SQUARE_SPACING: f64 = 4.;
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ImageOptions {
+    pub corner_arrows: bool,
+    pub edge_arrows: bool,
+    pub stickering: Stickering,
+    pub style: ImageStyle,
+}
+
+fn u_sticker_center(i: usize) -> (f64, f64) {
+    let col = (i % 3) as f64 - 1.;
+    let row = (i / 3) as f64 - 1.;
+    (FWIDTH / 2. + col * (STICKER_SIZE + SQUARE_SPACING),
+     FHEIGHT / 2. + row * (STICKER_SIZE + SQUARE_SPACING))
+}
+
+// Figures out where each piece in the given slots needs to go to be solved. Pieces are
+// identified by the set of stickers they're made of, so this works whether or not they're
+// oriented. Gives (from, to) pairs of U sticker indices, skipping pieces that are already home.
+fn piece_destinations(c: &CubeState, slots: &[&[usize]]) -> Vec<(usize, usize)> {
+    let piece_in = |slot: &[usize]| {
+        let mut stickers: Vec<u8> = slot.iter().map(|&i| c.state[i]).collect();
+        stickers.sort();
+        stickers
+    };
+
+    let mut result = vec![];
+    for (from, slot) in slots.iter().enumerate() {
+        let piece = piece_in(slot);
+        let home = slots.iter().position(|home_slot| {
+            let mut stickers: Vec<u8> = home_slot.iter().map(|&i| i as u8).collect();
+            stickers.sort();
+            stickers == piece
+        });
+        match home {
+            Some(to) if to != from => result.push((slot[0], slots[to][0])),
+            _ => {},
+        }
+    }
+    result
+}
+
+fn draw_arrow_head(cr: &mut ::cairo::Cairo, (x, y): (f64, f64), (dx, dy): (f64, f64)) {
+    cr.new_path();
+    cr.move_to(x, y);
+    cr.line_to(x - dx * ARROW_HEAD_LENGTH - dy * ARROW_HEAD_WIDTH,
+               y - dy * ARROW_HEAD_LENGTH + dx * ARROW_HEAD_WIDTH);
+    cr.line_to(x - dx * ARROW_HEAD_LENGTH + dy * ARROW_HEAD_WIDTH,
+               y - dy * ARROW_HEAD_LENGTH - dx * ARROW_HEAD_WIDTH);
+    cr.close_path();
+    cr.fill();
+}
 
-pub fn generate_image(c: CubeState, output_filename: &str) {
+fn draw_arrow(cr: &mut ::cairo::Cairo, from: usize, to: usize, double_headed: bool) {
+    let (x1, y1) = u_sticker_center(from);
+    let (x2, y2) = u_sticker_center(to);
+    let len = ((x2 - x1) * (x2 - x1) + (y2 - y1) * (y2 - y1)).sqrt();
+    let (dx, dy) = ((x2 - x1) / len, (y2 - y1) / len);
+    let start = (x1 + dx * ARROW_MARGIN, y1 + dy * ARROW_MARGIN);
+    let end = (x2 - dx * ARROW_MARGIN, y2 - dy * ARROW_MARGIN);
+
+    cr.set_source_rgba(0., 0., 0., 1.);
+    cr.set_line_width(ARROW_LINE_WIDTH);
+    cr.new_path();
+    cr.move_to(start.0, start.1);
+    cr.line_to(end.0, end.1);
+    cr.stroke();
+
+    draw_arrow_head(cr, end, (dx, dy));
+    if double_headed {
+        draw_arrow_head(cr, start, (-dx, -dy));
+    }
+}
+
+fn draw_arrows(cr: &mut ::cairo::Cairo, c: &CubeState, slots: &[&[usize]]) {
+    let moves = piece_destinations(c, slots);
+    for &(from, to) in moves.iter() {
+        // swaps are drawn as one arrow with a head at each end
+        let swap = moves.contains(&(to, from));
+        if !swap || from < to {
+            draw_arrow(cr, from, to, swap);
+        }
+    }
+}
+
+// Draws the case into a FWIDTH x FHEIGHT area at the origin.
+pub fn draw_case(cr: &mut ::cairo::Cairo, c: CubeState, options: &ImageOptions) {
     // this is inside this function because on the rust 1.15 nightly I get a segfault otherwise
     let stickers: [(usize, Sticker); 21] = [
         (51, Sticker::Vanishing {
@@ -270,15 +408,99 @@ pub fn generate_image(c: CubeState, output_filename: &str) {
         }),
     ];
 
-    let mut surface = Surface::create_image(Format::ARGB32, WIDTH, HEIGHT);
-    let mut cr = Cairo::create(&mut surface);
-
-    cr.set_line_width(4.);
+    cr.set_line_width(options.style.line_width);
 
     for &r in stickers.to_vec().iter() {
         let (i, s) = r;
-        draw_sticker(&mut cr, &s, sticker_color(c.state[i]).rgb());
+        draw_sticker(cr, &s, options.style.colors.rgb(options.stickering.face(c.state[i])));
+    }
+
+    if options.corner_arrows {
+        draw_arrows(cr, &c, &CORNER_SLOTS);
     }
+    if options.edge_arrows {
+        draw_arrows(cr, &c, &EDGE_SLOTS);
+    }
+}
+
+pub fn generate_image(c: CubeState, output_filename: &str, options: &ImageOptions) {
+    let style = &options.style;
+    let (image_width, image_height) = style.image_size(FWIDTH, FHEIGHT);
+    let mut surface = Surface::create_image(Format::ARGB32, image_width, image_height);
+    let mut cr = Cairo::create(&mut surface);
+
+    set_up_canvas(&mut cr, style, FWIDTH, FHEIGHT);
+    draw_case(&mut cr, c, options);
 
     surface.write_to_png(output_filename);
 }
+
+#[test]
+fn finds_no_destinations_when_solved() {
+    assert_eq!(piece_destinations(&CubeState::solved(), &CORNER_SLOTS), vec![]);
+    assert_eq!(piece_destinations(&CubeState::solved(), &EDGE_SLOTS), vec![]);
+}
+
+#[test]
+fn finds_piece_destinations() {
+    use algorithm::Algorithm;
+    use std::str::FromStr;
+
+    // U perm: cycles three edges and leaves the corners alone
+    let alg = Algorithm::from_str("R U' R U R U R U' R' U' R2").unwrap();
+    let case = alg.inverse().cube();
+    assert_eq!(piece_destinations(&case, &CORNER_SLOTS), vec![]);
+    assert_eq!(piece_destinations(&case, &EDGE_SLOTS).len(), 3);
+
+    // T perm: swaps two corners and two edges
+    let alg = Algorithm::from_str("R U R' U' R' F R2 U' R' U' R U R' F'").unwrap();
+    let case = alg.inverse().cube();
+    assert_eq!(piece_destinations(&case, &CORNER_SLOTS), vec![(2, 8), (8, 2)]);
+    assert_eq!(piece_destinations(&case, &EDGE_SLOTS), vec![(5, 3), (3, 5)]);
+}
+
+#[test]
+fn stickerings_hide_stickers() {
+    // a U sticker
+    assert!(Stickering::OLL.shows(0));
+    // the R sticker of a corner
+    assert!(!Stickering::OLL.shows(17));
+    assert!(Stickering::COLL.shows(17));
+    // the R sticker of an edge
+    assert!(!Stickering::COLL.shows(16));
+    assert!(Stickering::ZBLL.shows(16));
+    // a sticker from the F2L
+    assert!(!Stickering::ZBLL.shows(21));
+    assert!(Stickering::Full.shows(21));
+}
+
+#[test]
+fn stickerings_follow_the_pieces() {
+    use algorithm::Algorithm;
+    let faces = |stickering: Stickering, alg: &str| -> Vec<Option<Face>> {
+        let case = Algorithm::from_str(alg).unwrap().inverse().cube();
+        case.state.iter().map(|&sticker| stickering.face(sticker)).collect()
+    };
+    // A perm: three corners cycled, so their side stickers are all in each other's places
+    let a_perm = "R' F R' B2 R F' R' B2 R2";
+    for &stickering in [Stickering::COLL, Stickering::ZBLL].iter() {
+        let shown = faces(stickering, a_perm);
+        for slot in CORNER_SLOTS.iter() {
+            assert!(slot.iter().all(|&position| shown[position].is_some()));
+        }
+        // the F2L stays grey
+        assert!((18..51).all(|position| shown[position].is_none()));
+    }
+    let (coll, zbll) = (faces(Stickering::COLL, a_perm), faces(Stickering::ZBLL, a_perm));
+    for slot in CORNER_SLOTS.iter() {
+        assert!(slot.iter().all(|&position| coll[position] == zbll[position]));
+    }
+
+    // With the F2L broken up, it's the last layer's corners that are shown, wherever they are,
+    // and not the F2L's ones that have taken their places.
+    for &stickering in [Stickering::COLL, Stickering::ZBLL].iter() {
+        let shown = faces(stickering, "R");
+        assert_eq!((shown[2], shown[17]), (None, None));
+        assert!(shown.iter().filter(|f| f.is_some()).count() > 9);
+    }
+}
@@ -0,0 +1,287 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use rustc_serialize::json::{Json, Object};
+use generator::Face;
+use lla_error::LLAError;
+
+pub type Rgb = (f64, f64, f64);
+
+pub fn sticker_face(i: u8) -> Face {
+    match i {
+        0  | 1  | 2  | 3  | 4  | 5  | 6  | 7  | 8  => Face::U,
+        9  | 10 | 11 | 18 | 19 | 20 | 27 | 28 | 29 => Face::L,
+        12 | 13 | 14 | 21 | 22 | 23 | 30 | 31 | 32 => Face::F,
+        15 | 16 | 17 | 24 | 25 | 26 | 33 | 34 | 35 => Face::R,
+        36 | 37 | 38 | 39 | 40 | 41 | 42 | 43 | 44 => Face::D,
+        45 | 46 | 47 | 48 | 49 | 50 | 51 | 52 | 53 => Face::B,
+        _ => panic!("Don't have a face for {}", i)
+    }
+}
+
+const FACE_NAMES: [(&'static str, Face); 6] = [
+    ("U", Face::U), ("D", Face::D), ("F", Face::F), ("B", Face::B), ("R", Face::R), ("L", Face::L),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorScheme {
+    // indexed by `Face`
+    faces: [Rgb; 6],
+    // for stickers a stickering hides
+    hidden: Rgb,
+}
+
+impl Default for ColorScheme {
+    // yellow on top, green in front
+    fn default() -> Self {
+        ColorScheme {
+            faces: [
+                (1.,  1., 0.), // U
+                (1.,  1., 1.), // D
+                (0.,  1., 0.), // F
+                (0.,  0., 1.), // B
+                (1., 0.5, 0.), // R
+                (1.,  0., 0.), // L
+            ],
+            hidden: (0.5, 0.5, 0.5),
+        }
+    }
+}
+
+impl ColorScheme {
+    // Japanese colour scheme: white on top with blue opposite, green in front.
+    pub fn japanese() -> Self {
+        ColorScheme {
+            faces: [
+                (1.,  1., 1.), // U
+                (0.,  0., 1.), // D
+                (0.,  1., 0.), // F
+                (1.,  1., 0.), // B
+                (1.,  0., 0.), // R
+                (1., 0.5, 0.), // L
+            ],
+            hidden: (0.5, 0.5, 0.5),
+        }
+    }
+
+    // The Okabe-Ito palette, with a reddish purple in place of red so that it doesn't get mixed
+    // up with orange.
+    pub fn colorblind() -> Self {
+        ColorScheme {
+            faces: [
+                (0.941, 0.894, 0.259), // U
+                (1.,    1.,    1.   ), // D
+                (0.,    0.620, 0.451), // F
+                (0.,    0.447, 0.698), // B
+                (0.902, 0.624, 0.   ), // R
+                (0.800, 0.475, 0.655), // L
+            ],
+            hidden: (0.5, 0.5, 0.5),
+        }
+    }
+
+    pub fn named(name: &str) -> Result<Self, LLAError> {
+        match name {
+            "default"    => Ok(Self::default()),
+            "japanese"   => Ok(Self::japanese()),
+            "colorblind" => Ok(Self::colorblind()),
+            &_ => Err(LLAError::InvalidOption(format!("No colour scheme '{}'", name))),
+        }
+    }
+
+    pub fn face(&self, f: Face) -> Rgb {
+        self.faces[f as usize]
+    }
+
+    // `None` is a hidden sticker.
+    pub fn rgb(&self, f: Option<Face>) -> Rgb {
+        match f {
+            Some(f) => self.face(f),
+            None => self.hidden,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageStyle {
+    // Each view has its own natural size, which is used if neither of these is given. If only
+    // one is, the other keeps the view's proportions.
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub line_width: f64,
+    // `None` leaves the background transparent
+    pub background: Option<Rgb>,
+    pub colors: ColorScheme,
+}
+
+impl Default for ImageStyle {
+    fn default() -> Self {
+        ImageStyle {
+            width: None,
+            height: None,
+            line_width: 4.,
+            background: None,
+            colors: ColorScheme::default(),
+        }
+    }
+}
+
+fn parse_hex(s: &str) -> Result<Rgb, LLAError> {
+    let invalid = || LLAError::InvalidOption(format!("Bad colour '{}', expected something like #ffd500", s));
+    // checked for ASCII first so that slicing it by bytes can't land inside a character
+    if !s.is_ascii() || s.len() != 7 || !s.starts_with('#') {
+        return Err(invalid());
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(&s[i..i + 2], 16)
+            .map(|c| c as f64 / 255.)
+            .map_err(|_| invalid())
+    };
+    Ok((channel(1)?, channel(3)?, channel(5)?))
+}
+
+// A width or height, which has to be a whole number of pixels, at least one.
+fn size_field(obj: &Object, key: &str) -> Result<Option<i32>, LLAError> {
+    match number_field(obj, key)? {
+        Some(n) if n >= 1. && n <= i32::max_value() as f64 => Ok(Some(n as i32)),
+        Some(_) => Err(LLAError::InvalidOption(format!("'{}' should be at least 1", key))),
+        None => Ok(None),
+    }
+}
+
+fn string_field<'a>(obj: &'a Object, key: &str) -> Result<Option<&'a str>, LLAError> {
+    match obj.get(key) {
+        Some(v) => v.as_string()
+            .map(Some)
+            .ok_or(LLAError::InvalidOption(format!("'{}' should be a string", key))),
+        None => Ok(None),
+    }
+}
+
+fn number_field(obj: &Object, key: &str) -> Result<Option<f64>, LLAError> {
+    match obj.get(key) {
+        Some(v) => v.as_f64()
+            .map(Some)
+            .ok_or(LLAError::InvalidOption(format!("'{}' should be a number", key))),
+        None => Ok(None),
+    }
+}
+
+impl ImageStyle {
+    // Reads a style from JSON like
+    //
+    //   {
+    //     "scheme": "japanese",
+    //     "colors": { "U": "#ffffff", "hidden": "#404040" },
+    //     "width": 1000,
+    //     "height": 500,
+    //     "line_width": 3,
+    //     "background": "#ffffff"
+    //   }
+    //
+    // Every field is optional, anything left out keeps its default. "colors" overrides single
+    // faces of the chosen scheme.
+    pub fn from_json(s: &str) -> Result<Self, Box<Error>> {
+        let json = Json::from_str(s)?;
+        let obj = json.as_object().ok_or(LLAError::InvalidOption(String::from("Image style should be an object")))?;
+        let mut style = Self::default();
+
+        if let Some(name) = string_field(obj, "scheme")? {
+            style.colors = ColorScheme::named(name)?;
+        }
+        if let Some(colors) = obj.get("colors") {
+            let colors = colors.as_object().ok_or(LLAError::InvalidOption(String::from("'colors' should be an object")))?;
+            for &(name, face) in FACE_NAMES.iter() {
+                if let Some(hex) = string_field(colors, name)? {
+                    style.colors.faces[face as usize] = parse_hex(hex)?;
+                }
+            }
+            if let Some(hex) = string_field(colors, "hidden")? {
+                style.colors.hidden = parse_hex(hex)?;
+            }
+        }
+        if let Some(width) = size_field(obj, "width")? {
+            style.width = Some(width);
+        }
+        if let Some(height) = size_field(obj, "height")? {
+            style.height = Some(height);
+        }
+        if let Some(line_width) = number_field(obj, "line_width")? {
+            style.line_width = line_width;
+        }
+        if let Some(hex) = string_field(obj, "background")? {
+            style.background = Some(parse_hex(hex)?);
+        }
+        Ok(style)
+    }
+
+    pub fn load(filename: &str) -> Result<Self, Box<Error>> {
+        let mut file = File::open(&Path::new(filename))?;
+        let mut s = String::new();
+        file.read_to_string(&mut s)?;
+        Self::from_json(s.as_str())
+    }
+
+    // The size of the image for a view whose natural size is `width` x `height`.
+    pub fn image_size(&self, width: f64, height: f64) -> (i32, i32) {
+        match (self.width, self.height) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, (height * w as f64 / width).round() as i32),
+            (None, Some(h)) => ((width * h as f64 / height).round() as i32, h),
+            (None, None) => (width.round() as i32, height.round() as i32),
+        }
+    }
+
+    // Like `load`, but falls back to the default style if there's no such file.
+    pub fn load_or_default(filename: &str) -> Result<Self, Box<Error>> {
+        if Path::new(filename).exists() {
+            Self::load(filename)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+#[test]
+fn parses_hex_colours() {
+    assert_eq!(parse_hex("#ff0000").unwrap(), (1., 0., 0.));
+    assert_eq!(parse_hex("#00ff00").unwrap(), (0., 1., 0.));
+    assert!(parse_hex("ff0000").is_err());
+    assert!(parse_hex("#ff00zz").is_err());
+    assert!(parse_hex("#1é234").is_err());
+}
+
+#[test]
+fn loads_style_from_json() {
+    let style = ImageStyle::from_json(r##"{
+        "scheme": "japanese",
+        "colors": { "F": "#000000" },
+        "width": 1000,
+        "background": "#ffffff"
+    }"##).unwrap();
+    assert_eq!(style.colors.face(Face::U), (1., 1., 1.));
+    assert_eq!(style.colors.face(Face::F), (0., 0., 0.));
+    assert_eq!(style.width, Some(1000));
+    assert_eq!(style.height, None);
+    assert_eq!(style.background, Some((1., 1., 1.)));
+
+    assert!(ImageStyle::from_json(r#"{ "width": 0 }"#).is_err());
+    assert!(ImageStyle::from_json(r#"{ "height": -100 }"#).is_err());
+}
+
+#[test]
+fn keeps_proportions_of_view() {
+    let mut style = ImageStyle::default();
+    assert_eq!(style.image_size(500., 250.), (500, 250));
+    style.width = Some(1000);
+    assert_eq!(style.image_size(500., 250.), (1000, 500));
+    style.height = Some(100);
+    assert_eq!(style.image_size(500., 250.), (1000, 100));
+}
+
+#[test]
+fn empty_style_is_default() {
+    assert_eq!(ImageStyle::from_json("{}").unwrap(), ImageStyle::default());
+    assert!(ImageStyle::from_json(r#"{"scheme": "nope"}"#).is_err());
+}
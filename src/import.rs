@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use algorithm::Algorithm;
+use cases::{CaseNames, case_key, subset};
+
+// Checking a list of algs from somewhere else before taking it on: that each one can be read, is
+// a last layer alg, isn't in the list twice, and is called the same as the case it solves is
+// called everywhere else.
+
+// An alg as it was in the file, with the line it was on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportEntry {
+    pub line: usize,
+    pub name: Option<String>,
+    pub text: String,
+}
+
+// Splits a line of CSV into its fields, which can be in double quotes (with "" for a quote) if
+// they have commas in.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(::std::mem::replace(&mut field, String::new())),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|f| String::from(f.trim())).collect()
+}
+
+// Reads a file of algs, either one a line or as CSV with a name and then the alg, the way `sheet`
+// takes them. A CSV file can instead start with a header line saying which columns are which, the
+// alg being in the one headed "alg" and the name in one headed "name" or "case". Blank lines and
+// ones starting with '#' are skipped.
+pub fn parse_import(s: &str) -> Vec<ImportEntry> {
+    let mut columns = (Some(0), 1);
+    let mut result = vec![];
+    let mut first = true;
+    for (idx, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = csv_fields(line);
+        let is_header = first && fields.len() > 1 && fields.iter().any(|f| f.to_lowercase() == "alg");
+        first = false;
+        if is_header {
+            let column = |names: &[&str]| fields.iter().position(|f| names.contains(&f.to_lowercase().as_str()));
+            columns = (column(&["name", "case"]), column(&["alg"]).unwrap());
+            continue;
+        }
+        let (name, text) = if fields.len() == 1 {
+            (None, fields[0].clone())
+        } else {
+            (columns.0.and_then(|i| fields.get(i)).cloned(), fields.get(columns.1).cloned().unwrap_or(String::new()))
+        };
+        result.push(ImportEntry {
+            line: idx + 1,
+            name: name.and_then(|n| if n.is_empty() { None } else { Some(n) }),
+            text: text,
+        });
+    }
+    result
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    Unreadable(String),
+    BreaksF2L,
+    // all it does is turn the U layer, if that
+    NothingToSolve,
+    // the same alg, maybe held another way round, as on an earlier line
+    Duplicate(usize),
+    // the case has this name in the list of case names
+    WrongLabel(String),
+    // an earlier line gave the same case this other name
+    LabelledDifferently(usize, String),
+    // an earlier line gave this name to a different case
+    LabelReused(usize),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Problem::Unreadable(ref why) => write!(f, "can't be read: {}", why),
+            &Problem::BreaksF2L => write!(f, "breaks the F2L"),
+            &Problem::NothingToSolve => write!(f, "doesn't do anything to the last layer but turn it"),
+            &Problem::Duplicate(line) => write!(f, "is the same alg as line {}", line),
+            &Problem::WrongLabel(ref name) => write!(f, "solves {}, going by the case names", name),
+            &Problem::LabelledDifferently(line, ref name) => write!(f, "line {} calls the same case {}", line, name),
+            &Problem::LabelReused(line) => write!(f, "line {} gives the same name to a different case", line),
+        }
+    }
+}
+
+pub struct CheckedAlg {
+    pub entry: ImportEntry,
+    // None if it couldn't be read
+    pub alg: Option<Algorithm>,
+    // what the list of case names calls the case, if anything
+    pub case_name: Option<String>,
+    pub problems: Vec<Problem>,
+}
+
+impl Display for CheckedAlg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.entry.line)?;
+        if let Some(ref name) = self.entry.name {
+            write!(f, "{}, ", name)?;
+        }
+        writeln!(f, "{}", self.entry.text)?;
+        if let Some(ref alg) = self.alg {
+            if alg.is_ll() {
+                write!(f, "  solves {} case {}", subset(alg), case_key(alg))?;
+                match self.case_name {
+                    Some(ref name) => writeln!(f, " ({})", name)?,
+                    None => writeln!(f, "")?,
+                }
+            }
+        }
+        for problem in &self.problems {
+            writeln!(f, "  ! {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+// Checks each alg, against `names` and the others in the list.
+pub fn check_algs(entries: Vec<ImportEntry>, names: &CaseNames) -> Vec<CheckedAlg> {
+    // the first line each alg, case and name came up on
+    let mut algs_seen: HashMap<String, usize> = HashMap::new();
+    let mut labels_by_case: HashMap<String, (usize, String)> = HashMap::new();
+    let mut cases_by_label: HashMap<String, (usize, String)> = HashMap::new();
+    let mut result = vec![];
+
+    for entry in entries {
+        let mut problems = vec![];
+        let alg = match Algorithm::parse_extended(entry.text.as_str()) {
+            Ok(alg) => Some(alg),
+            Err(why) => {
+                problems.push(Problem::Unreadable(format!("{}", why)));
+                None
+            },
+        };
+        let case_name = alg.as_ref().and_then(|alg| names.name(alg)).map(String::from);
+        if let Some(ref alg) = alg {
+            if !alg.keeps_f2l() {
+                problems.push(Problem::BreaksF2L);
+            } else if !alg.is_ll() {
+                problems.push(Problem::NothingToSolve);
+            } else {
+                let key = case_key(alg);
+                let canonical = format!("{}", alg.canonical_rotation());
+                match algs_seen.get(&canonical) {
+                    Some(&line) => problems.push(Problem::Duplicate(line)),
+                    None => { algs_seen.insert(canonical, entry.line); },
+                }
+                if let Some(ref label) = entry.name {
+                    match case_name {
+                        Some(ref name) if name != label => problems.push(Problem::WrongLabel(name.clone())),
+                        _ => (),
+                    }
+                    match labels_by_case.get(&key) {
+                        Some(&(line, ref other)) if other != label => problems.push(Problem::LabelledDifferently(line, other.clone())),
+                        _ => (),
+                    }
+                    match cases_by_label.get(label) {
+                        Some(&(line, ref other)) if *other != key => problems.push(Problem::LabelReused(line)),
+                        _ => (),
+                    }
+                    labels_by_case.entry(key.clone()).or_insert((entry.line, label.clone()));
+                    cases_by_label.entry(label.clone()).or_insert((entry.line, key));
+                }
+            }
+        }
+        result.push(CheckedAlg { entry: entry, alg: alg, case_name: case_name, problems: problems });
+    }
+    result
+}
+
+#[test]
+fn reads_alg_lists() {
+    let entries = parse_import("R U R' U R U2 R'\n\n# comment\nSune,RUR'URU2R'\n\"T, perm\",R U R' U' R' F R2 U' R' U' R U R' F'\n");
+    assert_eq!(entries, vec![
+        ImportEntry { line: 1, name: None, text: String::from("R U R' U R U2 R'") },
+        ImportEntry { line: 4, name: Some(String::from("Sune")), text: String::from("RUR'URU2R'") },
+        ImportEntry { line: 5, name: Some(String::from("T, perm")), text: String::from("R U R' U' R' F R2 U' R' U' R U R' F'") },
+    ]);
+
+    let entries = parse_import("Alg,Set,Case\nR U R' U R U2 R',OLL,Sune\nR U2 R' U' R U' R',OLL,\n");
+    assert_eq!(entries, vec![
+        ImportEntry { line: 2, name: Some(String::from("Sune")), text: String::from("R U R' U R U2 R'") },
+        ImportEntry { line: 3, name: None, text: String::from("R U2 R' U' R U' R'") },
+    ]);
+}
+
+#[test]
+fn finds_problems() {
+    let names = CaseNames::parse("Sune,R U R' U R U2 R'\nAntisune,R U2 R' U' R U' R'").unwrap();
+    let checked = check_algs(parse_import("
+Sune,R U R' U R U2 R'
+Sune,y F U F' U F U2 F'
+Antisune,U R U R' U R U2 R'
+My Sune,U L U L' U L U2 L'
+Antisune,R U2 R' U' R U' R'
+T perm,R U R' U' R' F R2 U' R' U' R U R' F'
+T perm,R U R' U R U2 R'
+R U R'
+U2
+R Q
+"), &names);
+    let problems: Vec<Vec<Problem>> = checked.iter().map(|c| c.problems.clone()).collect();
+    assert_eq!(problems, vec![
+        vec![],
+        vec![Problem::Duplicate(2)],
+        vec![Problem::WrongLabel(String::from("Sune")), Problem::LabelledDifferently(2, String::from("Sune"))],
+        // the one before, held the other way round
+        vec![Problem::Duplicate(4), Problem::WrongLabel(String::from("Sune")), Problem::LabelledDifferently(2, String::from("Sune"))],
+        vec![Problem::LabelReused(4)],
+        vec![],
+        vec![Problem::Duplicate(2), Problem::WrongLabel(String::from("Sune")),
+             Problem::LabelledDifferently(2, String::from("Sune")), Problem::LabelReused(7)],
+        vec![Problem::BreaksF2L],
+        vec![Problem::NothingToSolve],
+        vec![Problem::Unreadable(String::from("Invalid algorithm: No move 'Q'"))],
+    ]);
+    assert_eq!(checked[0].case_name, Some(String::from("Sune")));
+    assert!(format!("{}", checked[0]).contains("solves ZBLL case"));
+    assert!(format!("{}", checked[7]).contains("! breaks the F2L"));
+}
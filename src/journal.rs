@@ -0,0 +1,183 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use rustc_serialize::json;
+
+// What the bot has posted, and how far along it is with what it's posting now. Every change is
+// written out before going on to the next step, so after a crash or a failed run the next run
+// picks up the same alg where it left off: anywhere it's already posted to gets skipped, and an
+// image that's already uploaded gets reused rather than uploaded again.
+//
+// It's saved as JSON, by writing a new file and renaming it over the old one, so the file on disk
+// is always either the old state or the new one, never half of each.
+
+#[derive(RustcEncodable, RustcDecodable, Clone, Debug, PartialEq)]
+pub struct BackendProgress {
+    pub backend: String,
+    pub media_id: Option<String>,
+    pub post_id: Option<String>,
+}
+
+#[derive(RustcEncodable, RustcDecodable, Clone, Debug, PartialEq)]
+pub struct Pending {
+    pub alg: String,
+    pub status: String,
+    pub backends: Vec<BackendProgress>,
+}
+
+#[derive(RustcEncodable, RustcDecodable, Clone, Debug, PartialEq)]
+struct State {
+    last_posted: Option<String>,
+    pending: Option<Pending>,
+}
+
+pub struct Journal {
+    // `None` keeps it in memory, for dry runs
+    path: Option<PathBuf>,
+    state: State,
+}
+
+fn read_to_string(path: &Path) -> Result<String, Box<Error>> {
+    let mut s = String::new();
+    File::open(path)?.read_to_string(&mut s)?;
+    Ok(s)
+}
+
+impl Journal {
+    // Before there was a journal the last alg posted was all there was, alone in a file. If
+    // there's no journal yet but there is one of those it's carried over.
+    pub fn load(filename: &str, legacy_filename: &str) -> Result<Self, Box<Error>> {
+        let path = Path::new(filename);
+        let state = if path.exists() {
+            json::decode(read_to_string(path)?.as_str())?
+        } else if Path::new(legacy_filename).exists() {
+            let last = read_to_string(Path::new(legacy_filename))?;
+            State { last_posted: Some(String::from(last.trim())), pending: None }
+        } else {
+            State { last_posted: None, pending: None }
+        };
+        Ok(Journal { path: Some(path.to_path_buf()), state: state })
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Journal { path: None, state: State { last_posted: None, pending: None } }
+    }
+
+    // Carries on from where this one is, without changing it on disk.
+    pub fn without_saving(self) -> Self {
+        Journal { path: None, state: self.state }
+    }
+
+    pub fn last_posted(&self) -> Option<&str> {
+        self.state.last_posted.as_ref().map(|s| s.as_str())
+    }
+
+    pub fn pending(&self) -> Option<&Pending> {
+        self.state.pending.as_ref()
+    }
+
+    fn save(&self) -> Result<(), Box<Error>> {
+        let path = match self.path {
+            Some(ref p) => p,
+            None => return Ok(()),
+        };
+        let tmp = path.with_extension("tmp");
+        {
+            let mut file = File::create(&tmp)?;
+            file.write_all(json::encode(&self.state)?.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp, path)?;
+        // the rename itself only sticks once the directory's synced too. Not every platform
+        // lets you open a directory to do that, but where it doesn't the rename is durable anyway.
+        let dir = match path.parent() {
+            Some(d) if !d.as_os_str().is_empty() => d,
+            _ => Path::new("."),
+        };
+        if let Ok(d) = File::open(dir) {
+            let _ = d.sync_all();
+        }
+        Ok(())
+    }
+
+    pub fn start(&mut self, alg: &str, status: &str) -> Result<(), Box<Error>> {
+        self.state.pending = Some(Pending { alg: String::from(alg), status: String::from(status), backends: vec![] });
+        self.save()
+    }
+
+    pub fn progress(&self, backend: &str) -> Option<&BackendProgress> {
+        self.pending().and_then(|p| p.backends.iter().find(|b| b.backend == backend))
+    }
+
+    fn progress_mut(&mut self, backend: &str) -> Result<&mut BackendProgress, Box<Error>> {
+        let pending = self.state.pending.as_mut().ok_or("Nothing is being posted")?;
+        if let Some(idx) = pending.backends.iter().position(|b| b.backend == backend) {
+            return Ok(&mut pending.backends[idx]);
+        }
+        pending.backends.push(BackendProgress { backend: String::from(backend), media_id: None, post_id: None });
+        Ok(pending.backends.last_mut().unwrap())
+    }
+
+    pub fn record_media(&mut self, backend: &str, media_id: &str) -> Result<(), Box<Error>> {
+        self.progress_mut(backend)?.media_id = Some(String::from(media_id));
+        self.save()
+    }
+
+    // Forgets the uploaded image, e.g. once the platform's thrown it away for not being posted in
+    // time, so that it's uploaded again.
+    pub fn clear_media(&mut self, backend: &str) -> Result<(), Box<Error>> {
+        self.progress_mut(backend)?.media_id = None;
+        self.save()
+    }
+
+    pub fn record_post(&mut self, backend: &str, post_id: &str) -> Result<(), Box<Error>> {
+        self.progress_mut(backend)?.post_id = Some(String::from(post_id));
+        self.save()
+    }
+
+    // The pending alg is done with, and is what the next one follows on from.
+    pub fn finish(&mut self) -> Result<(), Box<Error>> {
+        if let Some(pending) = self.state.pending.take() {
+            self.state.last_posted = Some(pending.alg);
+        }
+        self.save()
+    }
+}
+
+#[test]
+fn survives_being_reloaded_partway() {
+    let dir = ::std::env::temp_dir().join(format!("lla-journal-{}", ::std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let filename = dir.join("state.json");
+    let filename = filename.to_str().unwrap();
+    let legacy = dir.join("last");
+    File::create(&legacy).unwrap().write_all(b"R U R' U R U2 R'\n").unwrap();
+
+    let mut journal = Journal::load(filename, legacy.to_str().unwrap()).unwrap();
+    assert_eq!(journal.last_posted(), Some("R U R' U R U2 R'"));
+    journal.start("R U2 R' U' R U' R'", "R U2 R' U' R U' R'").unwrap();
+    journal.record_media("twitter", "1234").unwrap();
+    journal.record_media("mastodon", "22").unwrap();
+    journal.record_post("mastodon", "109").unwrap();
+    journal.record_media("webhook", "image.png").unwrap();
+    journal.clear_media("webhook").unwrap();
+
+    // as if it had crashed here
+    let mut journal = Journal::load(filename, legacy.to_str().unwrap()).unwrap();
+    assert_eq!(journal.pending().unwrap().alg, "R U2 R' U' R U' R'");
+    assert_eq!(journal.progress("twitter"), Some(&BackendProgress {
+        backend: String::from("twitter"), media_id: Some(String::from("1234")), post_id: None,
+    }));
+    assert_eq!(journal.progress("mastodon").unwrap().post_id, Some(String::from("109")));
+    assert_eq!(journal.progress("webhook").unwrap().media_id, None);
+
+    journal.finish().unwrap();
+    let journal = Journal::load(filename, legacy.to_str().unwrap()).unwrap();
+    assert_eq!(journal.last_posted(), Some("R U2 R' U' R U' R'"));
+    assert!(journal.pending().is_none());
+    assert!(!dir.join("state.tmp").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
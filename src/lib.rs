@@ -0,0 +1,114 @@
+//! Enumerating, identifying and drawing algs for the last layer of a 3x3x3 cube.
+//!
+//! The parts meant for use from other crates are:
+//!
+//! * `Algorithm`, a sequence of moves. It parses from the usual notation with `from_str`, and
+//!   can be inverted, mirrored and turned into the `CubeState` it leaves a solved cube in.
+//! * `CubeState`, where each sticker of a cube is. `apply` does one state's permutation to
+//!   another, so `a.cube().apply(&b.cube())` is the state doing `a` then `b` leaves.
+//! * `AlgorithmIterator`, every last layer alg in order of length, optionally starting after a
+//!   given one.
+//! * `cases`, for telling which case an alg solves: `case_key` is the same for any two algs
+//!   solving the same case, `subset` says whether it's PLL, OLL, ZBLL and so on, and `CaseNames`
+//!   looks names up from a list. `verify` says whether an alg solves a case, and with which AUFs.
+//! * `record::AlgRecord`, all of that for one alg at once, which `RecordWriter` writes out as
+//!   JSON, JSON lines or CSV, and `database::AlgDatabase`, which keeps them in SQLite.
+//! * `import::check_algs`, for checking a list of algs from elsewhere, read with
+//!   `Algorithm::parse_extended`, which takes the looser ways algs tend to be written.
+//! * `optimal`, every last layer state and the shortest algs for each case, and
+//!   `scramble::Scrambler`, for scrambling random ones.
+//! * `image_generator::generate_image`, `cube_image` and `animation` for drawing cases to PNG
+//!   and GIF files, and `terminal_diagram::render` for printing them.
+//!
+//! ```
+//! extern crate last_layer_algs;
+//! use std::str::FromStr;
+//! use last_layer_algs::{Algorithm, AlgorithmIterator, CubeState, case_key, subset};
+//!
+//! # fn main() {
+//! let sune = Algorithm::from_str("R U R' U R U2 R'").unwrap();
+//! assert_eq!(sune.cube().apply(&sune.inverse().cube()), CubeState::solved());
+//! assert_eq!(subset(&sune), "ZBLL");
+//! // the same case, held the other way round
+//! assert_eq!(case_key(&Algorithm::from_str("U L U L' U L U2 L'").unwrap()), case_key(&sune));
+//!
+//! let after_sune = AlgorithmIterator::from_starting_algorithm("R U R' U R U2 R'").unwrap().next().unwrap();
+//! assert!(after_sune.length() >= sune.length());
+//! # }
+//! ```
+//!
+//! The modules for posting algs (`publisher` and the rest) are public for the command line's
+//! sake, but may change from one version to the next.
+
+#[macro_use]
+extern crate lazy_static;
+extern crate cairo;
+extern crate curl;
+extern crate crypto;
+extern crate rand;
+extern crate toml;
+extern crate rusqlite;
+extern crate rustc_serialize;
+extern crate gif;
+extern crate png;
+
+// The cube, and algs for it
+pub mod generator;
+pub mod cubestate;
+pub mod algorithm;
+pub mod algorithm_iterator;
+pub mod lla_error;
+pub mod cases;
+pub mod record;
+pub mod database;
+pub mod import;
+pub mod counts;
+pub mod optimal;
+pub mod scramble;
+mod corner_permutation;
+mod corner_orientation;
+mod edge_orientation;
+mod edge_permutation;
+mod f2l_cubestate;
+mod prunable;
+
+// Drawing cases
+pub mod image_style;
+pub mod image_generator;
+pub mod cube_image;
+pub mod animation;
+pub mod terminal_diagram;
+pub mod sheet;
+
+// Posting them
+#[doc(hidden)] pub mod oauth;
+#[doc(hidden)] pub mod http;
+#[doc(hidden)] pub mod publisher;
+#[doc(hidden)] pub mod tweet;
+#[doc(hidden)] pub mod mastodon;
+#[doc(hidden)] pub mod webhook;
+#[doc(hidden)] pub mod journal;
+#[doc(hidden)] pub mod history;
+#[doc(hidden)] pub mod template;
+#[doc(hidden)] pub mod alt_text;
+#[doc(hidden)] pub mod schedule;
+#[doc(hidden)] pub mod daemon;
+#[cfg(test)]
+mod mock_server;
+
+pub use algorithm::Algorithm;
+pub use algorithm_iterator::AlgorithmIterator;
+pub use cases::{CaseNames, case_key, subset};
+pub use cubestate::CubeState;
+pub use lla_error::LLAError;
+
+// Builds the move and pruning tables the enumeration uses from scratch, for the benchmarks. The
+// ones it uses are made once, the first time they're needed.
+#[doc(hidden)]
+pub fn generate_tables() -> usize {
+    let tables = [corner_permutation::make_tables(), corner_orientation::make_tables(), edge_orientation::make_tables()];
+    tables.iter().map(|&(ref transitions, ref pruning)| transitions.len() + pruning.len()).sum()
+}
+
+#[doc(hidden)]
+pub use corner_orientation::{twist_corners, twist_corners_by_table};
@@ -4,14 +4,30 @@ use std::fmt::Display;
 #[derive(Debug)]
 pub enum LLAError {
     InvalidAlgorithm(String),
+    InvalidOption(String),
     IOError(::std::io::Error),
+    // Worth trying again in a bit, e.g. the server was down or the connection dropped.
+    Transient(String),
+    // The credentials were turned down.
+    Auth(String),
+    // Told to slow down, maybe with how many seconds until it's worth trying again.
+    RateLimited(String, Option<u64>),
+    // The platform no longer has the media a post was meant to include, e.g. it threw away an
+    // upload that sat unposted for too long.
+    MediaGone(String),
 }
 
 impl Display for LLAError {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         match self {
             &LLAError::InvalidAlgorithm(ref msg) => write!(f, "Invalid algorithm: {}", msg),
+            &LLAError::InvalidOption(ref msg) => write!(f, "Invalid option: {}", msg),
             &LLAError::IOError(ref err) => write!(f, "{}", err),
+            &LLAError::Transient(ref msg) => write!(f, "Temporary failure: {}", msg),
+            &LLAError::Auth(ref msg) => write!(f, "Not authorized: {}", msg),
+            &LLAError::RateLimited(ref msg, Some(secs)) => write!(f, "Rate limited for {}s: {}", secs, msg),
+            &LLAError::RateLimited(ref msg, None) => write!(f, "Rate limited: {}", msg),
+            &LLAError::MediaGone(ref msg) => write!(f, "Media not found: {}", msg),
         }
     }
 }
@@ -21,7 +37,12 @@ impl Error for LLAError {
     fn description(&self) -> &str {
         match self {
             &LLAError::InvalidAlgorithm(ref msg) => msg.as_str(),
+            &LLAError::InvalidOption(ref msg) => msg.as_str(),
             &LLAError::IOError(ref err) => err.description(),
+            &LLAError::Transient(ref msg) => msg.as_str(),
+            &LLAError::Auth(ref msg) => msg.as_str(),
+            &LLAError::RateLimited(ref msg, _) => msg.as_str(),
+            &LLAError::MediaGone(ref msg) => msg.as_str(),
         }
     }
 }
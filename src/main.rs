@@ -1,104 +1,893 @@
-#![feature(test)]
-
-#[macro_use]
-extern crate lazy_static;
-extern crate test;
 extern crate clap;
-extern crate cairo;
-extern crate oauth_client as oauth;
-extern crate rustc_serialize;
-
-use clap::{Arg, App, SubCommand};
-
-mod generator;
-mod cubestate;
-mod algorithm;
-mod algorithm_iterator;
-mod tweet;
-mod image_generator;
-mod lla_error;
-mod corner_permutation;
-mod corner_orientation;
-mod edge_orientation;
-mod edge_permutation;
-mod f2l_cubestate;
-mod prunable;
+extern crate last_layer_algs;
+extern crate rand;
+
+use clap::{Arg, App, ArgMatches, SubCommand};
 
 use std::str::FromStr;
-use algorithm::Algorithm;
-use algorithm_iterator::{AlgorithmIterator};
+use last_layer_algs::{alt_text, animation, cases, cube_image, image_generator, import, publisher, sheet, template, terminal_diagram};
+use last_layer_algs::{Algorithm, AlgorithmIterator, CaseNames, LLAError, case_key};
+use last_layer_algs::animation::AnimationOptions;
+use last_layer_algs::image_generator::{ImageOptions, Stickering};
+use last_layer_algs::image_style::{ColorScheme, ImageStyle};
+use last_layer_algs::cases::{AlgEntry, Verdict};
+use last_layer_algs::history::History;
+use last_layer_algs::import::Problem;
+use last_layer_algs::journal::Journal;
+use last_layer_algs::database::AlgDatabase;
+use last_layer_algs::counts::{LengthCounter, LengthCounts};
+use last_layer_algs::daemon::{self, Job, SystemClock};
+use last_layer_algs::schedule::Schedule;
+use last_layer_algs::optimal::OptimalTable;
+use last_layer_algs::scramble::Scrambler;
+use last_layer_algs::publisher::{DryRun, Publisher};
+use last_layer_algs::record::{AlgRecord, RecordFormat, RecordWriter};
+use last_layer_algs::template::PostDetails;
+use last_layer_algs::sheet::SheetLayout;
+use std::collections::HashSet;
 use std::error::Error;
+use std::fmt::Display;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{self, BufWriter, Read};
 use std::path::Path;
 
 // TODO: take this as a cli param?
+const STATE_FNAME: &'static str = "state.json";
+// What came before the state file, it's only read to carry it over
 const LAST_FNAME: &'static str = "last";
+const HISTORY_FNAME: &'static str = "history.jsonl";
+// How many posts back to look for the same case when picking what to post next
+const DEFAULT_RECENT_POSTS: usize = 50;
 const IMAGE_FNAME: &'static str = "output_file.png";
+const ANIMATION_FNAME: &'static str = "output_file.gif";
+const SHEET_FNAME: &'static str = "sheet.pdf";
+// How often `export` says how it's getting on
+const EXPORT_PROGRESS_EVERY: usize = 100000;
+// How wide the longest bar of a histogram is, in characters
+const HISTOGRAM_WIDTH: usize = 50;
+// Used for the images and diagrams if it exists and no other style is given
+const IMAGE_STYLE_FNAME: &'static str = "image_style.json";
 
-fn alg_following(s: &str) -> Result<String, Box<Error>> {
+fn alg_following(s: &str) -> Result<Algorithm, Box<Error>> {
     let mut it = AlgorithmIterator::from_starting_algorithm(s)?;
-    let alg = it.next().unwrap();
-    Ok(format!("{}", alg))
+    Ok(it.next().unwrap())
+}
+
+fn load_style(filename: Option<&str>) -> Result<ImageStyle, Box<Error>> {
+    match filename {
+        Some(f) => ImageStyle::load(f),
+        None => ImageStyle::load_or_default(IMAGE_STYLE_FNAME),
+    }
 }
 
-fn get_last_alg() -> Result<String, Box<Error>> {
-    let path = Path::new(LAST_FNAME);
+// `diagram` is the colour scheme to print a diagram of the case with, if we're printing one.
+fn print_alg(alg: &Algorithm, diagram: Option<&ColorScheme>) {
+    println!("{}", alg);
+    if let Some(colors) = diagram {
+        // the case an alg solves is the state its inverse produces
+        print!("{}", terminal_diagram::render(alg.inverse().cube(), colors));
+    }
+}
+
+fn load_case_names(config: &publisher::Config) -> Result<CaseNames, Box<Error>> {
+    match config.case_names {
+        Some(ref f) => CaseNames::parse(read_file(f)?.as_str()),
+        None => Ok(CaseNames::empty()),
+    }
+}
+
+// The case names given with --case-names, if any.
+fn case_names_from(matches: &ArgMatches) -> Result<CaseNames, Box<Error>> {
+    match matches.value_of("case-names") {
+        Some(f) => CaseNames::parse(read_file(f)?.as_str()),
+        None => Ok(CaseNames::empty()),
+    }
+}
+
+// Prints `count` algs (or all of them, forever, if it's None) from `algs` as records in the given
+// format.
+fn print_records<I: Iterator<Item=Algorithm>>(algs: I, format: RecordFormat, count: Option<usize>, case_names: &CaseNames) -> Result<(), Box<Error>> {
+    if format == RecordFormat::Json && count.is_none() {
+        return Err(From::from("A JSON array of every alg would never end, give a --count or use --format jsonl"));
+    }
+    let stdout = io::stdout();
+    let mut writer = RecordWriter::new(BufWriter::new(stdout.lock()), format);
+    for alg in algs.take(count.unwrap_or(usize::max_value())) {
+        writer.write(&AlgRecord::new(&alg, case_names))?;
+    }
+    writer.finish().map(|_| ())
+}
+
+fn read_file(filename: &str) -> Result<String, Box<Error>> {
+    let path = Path::new(filename);
     let mut file = File::open(&path)?;
     let mut s = String::new();
     file.read_to_string(&mut s)?;
     Ok(s)
 }
 
-fn write_alg(alg: String) -> Result<(), Box<Error>> {
-    let path = Path::new(LAST_FNAME);
-    File::create(&path)?.write_all(alg.as_bytes())?;
+// The next alg after `last` that doesn't solve the same case (or a mirror or inverse of it) as
+// any of the last `recent` posts.
+fn next_alg_to_post(last: &str, history: &History, recent: usize) -> Result<Algorithm, Box<Error>> {
+    let mut it = AlgorithmIterator::from_starting_algorithm(last)?;
+    loop {
+        let alg = it.next().unwrap();
+        if !history.posted_recently(&alg, recent) {
+            return Ok(alg);
+        }
+        println!("Skipping {}, its case was posted recently", alg);
+    }
+}
+
+// An alg that's been picked and had its image made, with everything needed to post it.
+struct PreparedPost {
+    journal: Journal,
+    history: History,
+    publishers: Vec<Box<Publisher>>,
+    alg: Algorithm,
+    media_fname: &'static str,
+    alt_text: String,
+}
+
+// Picks what to post next and gets it ready, leaving it pending in the journal. With `dry_run`
+// nothing gets posted, it's all left in that directory instead, and the state and history files
+// stay as they were.
+fn prepare_post(style_filename: Option<&str>, animate: bool, dry_run: Option<&str>, recent: usize) -> Result<PreparedPost, Box<Error>> {
+    let mut journal = Journal::load(STATE_FNAME, LAST_FNAME)?;
+    if dry_run.is_some() {
+        journal = journal.without_saving();
+    }
+    let history = History::load(HISTORY_FNAME)?;
+    // A run that didn't finish leaves its alg pending, and that gets another go before moving on
+    let alg_to_tweet = match journal.pending() {
+        Some(pending) => Algorithm::from_str(pending.alg.as_str())?,
+        None => next_alg_to_post(journal.last_posted().unwrap_or(""), &history, recent)?,
+    };
+    let config = publisher::Config::load()?;
+    let case_names = load_case_names(&config)?;
+    if journal.pending().is_none() {
+        let details = PostDetails {
+            alg: &alg_to_tweet,
+            index: history.len() + 1,
+            case_name: case_names.name(&alg_to_tweet),
+        };
+        let status = template::render(config.template.as_str(), &details)?;
+        journal.start(format!("{}", alg_to_tweet).as_str(), status.as_str())?;
+    }
+
+    let options = ImageOptions {
+        style: load_style(style_filename)?,
+        ..ImageOptions::default()
+    };
+    let media_fname = if animate {
+        animation::generate_animation(&alg_to_tweet, ANIMATION_FNAME, &options, &AnimationOptions::default())?;
+        ANIMATION_FNAME
+    } else {
+        image_generator::generate_image(alg_to_tweet.inverse().cube(), IMAGE_FNAME, &options);
+        IMAGE_FNAME
+    };
+    let publishers: Vec<Box<Publisher>> = match dry_run {
+        Some(dir) => vec![Box::new(DryRun::new(dir))],
+        None => config.publishers()?,
+    };
+    let alt_text = alt_text::describe(&alg_to_tweet, case_names.name(&alg_to_tweet));
+    Ok(PreparedPost {
+        journal: journal,
+        history: history,
+        publishers: publishers,
+        alg: alg_to_tweet,
+        media_fname: media_fname,
+        alt_text: alt_text,
+    })
+}
+
+fn publish_post(prepared: PreparedPost, dry_run: bool) -> Result<(), Box<Error>> {
+    let PreparedPost { mut journal, mut history, publishers, alg, media_fname, alt_text } = prepared;
+    let posts = publisher::publish_everywhere(&publishers, &mut journal, media_fname, alt_text.as_str())?;
+    if !dry_run {
+        history.record(&alg, posts)?;
+    }
+    journal.finish()
+}
+
+fn prepare_tweet(style_filename: Option<&str>, animate: bool, dry_run: Option<&str>, recent: usize) -> Result<(), Box<Error>> {
+    publish_post(prepare_post(style_filename, animate, dry_run, recent)?, dry_run.is_some())
+}
+
+// Posts for `serve`, getting each post ready ahead of time.
+struct ScheduledPoster<'a> {
+    style_filename: Option<&'a str>,
+    animate: bool,
+    recent: usize,
+    prepared: Option<PreparedPost>,
+}
+
+impl<'a> Job for ScheduledPoster<'a> {
+    fn prepare(&mut self) -> Result<String, Box<Error>> {
+        let prepared = prepare_post(self.style_filename, self.animate, None, self.recent)?;
+        let alg = format!("{}", prepared.alg);
+        self.prepared = Some(prepared);
+        Ok(alg)
+    }
+
+    fn post(&mut self) -> Result<(), Box<Error>> {
+        let prepared = match self.prepared.take() {
+            Some(prepared) => prepared,
+            None => prepare_post(self.style_filename, self.animate, None, self.recent)?,
+        };
+        publish_post(prepared, false)
+    }
+}
+
+fn render(alg: &str, output_filename: &str, options: &ImageOptions, view: &str, scramble: bool) -> Result<(), Box<Error>> {
+    let alg = Algorithm::from_str(alg)?;
+    // normally we draw the case the alg solves, but a scramble is drawn as the state it leaves
+    let cube = if scramble { alg.cube() } else { alg.inverse().cube() };
+    match view {
+        "net" => cube_image::generate_net_image(cube, output_filename, options),
+        "iso" => cube_image::generate_isometric_image(cube, output_filename, options),
+        _ => image_generator::generate_image(cube, output_filename, options),
+    }
+    Ok(())
+}
+
+fn animate(alg: &str, output_filename: &str, options: &ImageOptions, animation: &AnimationOptions) -> Result<(), Box<Error>> {
+    let alg = Algorithm::from_str(alg)?;
+    animation::generate_animation(&alg, output_filename, options, animation)
+}
+
+// The sheet has the algs in `algs_filename` if there is one, and otherwise the `count` algs the
+// enumeration gives after `from`.
+fn make_sheet(algs_filename: Option<&str>, from: &str, count: usize, output_filename: &str, layout: &SheetLayout, options: &ImageOptions) -> Result<(), Box<Error>> {
+    let entries = match algs_filename {
+        Some(f) => cases::parse_entries(read_file(f)?.as_str())?,
+        None => AlgorithmIterator::from_starting_algorithm(from)?
+            .take(count)
+            .map(|alg| AlgEntry { alg: alg, name: None })
+            .collect(),
+    };
+
+    if output_filename.ends_with(".png") {
+        let prefix = &output_filename[..output_filename.len() - ".png".len()];
+        for f in sheet::generate_png_sheets(&entries, prefix, layout, options) {
+            println!("{}", f);
+        }
+    } else {
+        sheet::generate_pdf_sheet(&entries, output_filename, layout, options);
+    }
+    Ok(())
+}
+
+// Adds the algs following `from` to the database, or if there's no `from` the ones following the
+// last alg an export put in it, so that an export that was stopped carries on where it left off. It
+// stops after `count` algs or once they get longer than `max_length`, or otherwise never.
+fn export(filename: &str, from: Option<&str>, count: Option<usize>, max_length: Option<usize>, case_names: &CaseNames) -> Result<(), Box<Error>> {
+    let mut db = AlgDatabase::open(filename)?;
+    let from = match from {
+        Some(alg) => String::from(alg),
+        None => db.enumerated_to()?.map(|alg| format!("{}", alg)).unwrap_or(String::new()),
+    };
+    let mut algs = AlgorithmIterator::from_starting_algorithm(from.as_str())?;
+    if let Some(max) = max_length {
+        algs = algs.with_max_length(max)?;
+    }
+    let mut added = 0;
+    for alg in algs.take(count.unwrap_or(usize::max_value())) {
+        db.add_enumerated(&alg, case_names)?;
+        added += 1;
+        if added % EXPORT_PROGRESS_EVERY == 0 {
+            eprintln!("{} algs added, up to {}", added, alg);
+        }
+    }
+    db.commit()?;
+    let (algs, cases) = db.counts()?;
+    println!("Added {} algs, {} now has {} algs for {} cases", added, filename, algs, cases);
+    Ok(())
+}
+
+// Checks the algs in the file, printing the case each one solves and anything wrong with it, and
+// adds the ones that are last layer algs to the database if there is one. Gives back how many had
+// something wrong.
+fn import_algs(filename: &str, database: Option<&str>, case_names: &CaseNames) -> Result<usize, Box<Error>> {
+    let checked = import::check_algs(import::parse_import(read_file(filename)?.as_str()), case_names);
+    let mut db = match database {
+        Some(f) => Some(AlgDatabase::open(f)?),
+        None => None,
+    };
+    let mut cases = HashSet::new();
+    for c in &checked {
+        print!("{}", c);
+        let alg = match c.alg {
+            Some(ref alg) if alg.is_ll() => alg,
+            _ => continue,
+        };
+        cases.insert(case_key(alg));
+        if let Some(ref mut db) = db {
+            let mut record = AlgRecord::new(alg, case_names);
+            let label_ok = !c.problems.iter().any(|p| match p {
+                &Problem::WrongLabel(_) | &Problem::LabelledDifferently(_, _) | &Problem::LabelReused(_) => true,
+                _ => false,
+            });
+            if record.case_name.is_none() && label_ok {
+                record.case_name = c.entry.name.clone();
+            }
+            db.add_record(alg, &record)?;
+        }
+    }
+    if let Some(ref mut db) = db {
+        db.commit()?;
+    }
+    let with_problems = checked.iter().filter(|c| !c.problems.is_empty()).count();
+    println!("{} algs for {} cases, {} with problems", checked.len(), cases.len(), with_problems);
+    Ok(with_problems)
+}
+
+// Says whether the alg solves the case the setup leaves, and if it doesn't, draws what's left.
+// Gives back whether it did.
+fn verify(alg: &str, setup: &str, colors: &ColorScheme) -> Result<bool, Box<Error>> {
+    let alg = Algorithm::parse_extended(alg)?;
+    match cases::verify(&alg, &Algorithm::parse_extended(setup)?) {
+        Verdict::Solves(aufs) => {
+            println!("Solves it: {}", aufs.around(&alg));
+            Ok(true)
+        },
+        Verdict::Leaves(aufs, left) => {
+            println!("Doesn't solve it. The closest is {}, which leaves:", aufs.around(&alg));
+            print!("{}", terminal_diagram::render(left, colors));
+            Ok(false)
+        },
+    }
+}
+
+// Prints a table of how many algs there are of each length up to `max_length`, and how many cases
+// they solve, only starting it at `min_length` but counting everything shorter for the totals.
+fn count(min_length: usize, max_length: usize) -> Result<(), Box<Error>> {
+    let algs = AlgorithmIterator::new().with_max_length(max_length)?;
+    println!("{:>6} {:>12} {:>8} {:>10} {:>13}", "length", "algs", "cases", "new cases", "cases so far");
+    let print_row = |row: LengthCounts| if row.length >= min_length {
+        println!("{:>6} {:>12} {:>8} {:>10} {:>13}", row.length, row.algs, row.cases, row.new_cases, row.cases_so_far);
+    };
+    let mut counter = LengthCounter::new();
+    for alg in algs {
+        if let Some(row) = counter.add(&alg) {
+            print_row(row);
+        }
+    }
+    if let Some(row) = counter.finish() {
+        print_row(row);
+    }
     Ok(())
 }
 
-fn prepare_tweet() -> Result<(), Box<Error>> {
-    let s = get_last_alg()?;
-    let alg_to_tweet = alg_following(s.as_str())?;
-    let inverted_alg = Algorithm::from_str(alg_to_tweet.as_str()).unwrap().inverse();
-    ::image_generator::generate_image(inverted_alg.cube(), IMAGE_FNAME);
-    ::tweet::tweet(alg_to_tweet.as_str(), IMAGE_FNAME)?;
-    write_alg(alg_to_tweet)?;
+fn print_histogram(title: &str, rows: Vec<(usize, usize)>) {
+    println!("\n{}", title);
+    let most = rows.iter().map(|&(_, n)| n).max().unwrap_or(0);
+    for (length, n) in rows {
+        println!("{:>5} {:>6} {}", length, n, "#".repeat(n * HISTOGRAM_WIDTH / most));
+    }
+}
 
+// Prints the shortest algs for every case, or every case in `subset`, in both metrics, as each is
+// solved, then how many cases need each number of moves. Mirrors and inverses of a case share its
+// row, which says how many cases it's for.
+fn optimal(subset: Option<&str>, case_names: &CaseNames) -> Result<(), Box<Error>> {
+    println!("{:<42} {:<5} {:>5} {:>4} {:>5} {:>4} {:>5}  {}", "case", "set", "cases", "htm", "algs", "qtm", "algs", "example");
+    let table = OptimalTable::new(subset, |case| {
+        let name = case_names.name_of_key(case.key.as_str()).map(|n| format!(" ({})", n)).unwrap_or(String::new());
+        println!("{:<42} {:<5} {:>5} {:>4} {:>5} {:>4} {:>5}  {}{}", case.key, case.subset, case.cases,
+                 case.htm.length, case.htm.solutions, case.qtm.length, case.qtm.solutions, case.htm.example, name);
+    });
+    print_histogram("Cases by optimal length (HTM)", table.histogram(|c| &c.htm));
+    print_histogram("Cases by optimal length (QTM)", table.histogram(|c| &c.qtm));
     Ok(())
 }
 
+// Prints scrambles for `count` random last layer states, from `subset` if it's given, made of
+// the algs up to `max_alg_length` moves long.
+fn scramble(count: usize, subset: Option<&str>, max_alg_length: usize, diagram: Option<&ColorScheme>) -> Result<(), Box<Error>> {
+    let scrambler = Scrambler::new(AlgorithmIterator::new().with_max_length(max_alg_length)?.collect());
+    let mut rng = rand::thread_rng();
+    for _ in 0..count {
+        let state = scrambler.random_state(&mut rng, subset).ok_or("no states in that subset")?;
+        let scramble = scrambler.scramble(&state)
+            .ok_or(format!("the algs up to {} moves long can't reach every state", max_alg_length))?;
+        println!("{}", scramble);
+        if let Some(colors) = diagram {
+            print!("{}", terminal_diagram::render(state, colors));
+        }
+    }
+    Ok(())
+}
+
+fn diagram_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("diagram")
+        .help("also print a coloured diagram of the case in the terminal")
+        .long("diagram")
+        .short("d")
+}
+
+fn style_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("style")
+        .help("JSON file giving the colours and size of images, defaults to image_style.json")
+        .long("style")
+        .takes_value(true)
+}
+
+fn format_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("format")
+        .help("print each alg as a JSON, JSON lines or CSV record giving its lengths, inverse, case and so on")
+        .long("format")
+        .possible_values(&["text", "json", "jsonl", "csv"])
+        .takes_value(true)
+}
+
+// None for plain text, which is the default.
+fn record_format(matches: &ArgMatches) -> Result<Option<RecordFormat>, LLAError> {
+    match matches.value_of("format") {
+        None | Some("text") => Ok(None),
+        Some(f) => RecordFormat::from_str(f).map(Some),
+    }
+}
+
+fn case_names_arg<'a, 'b>(help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name("case-names")
+        .help(help)
+        .long("case-names")
+        .value_name("FILE")
+        .takes_value(true)
+}
+
+fn animate_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("animate")
+        .help("post a GIF of the alg being done rather than a picture of the case")
+        .long("animate")
+}
+
+fn recent_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("recent")
+        .help("skip algs for cases posted in this many of the last posts (default 50, 0 to allow repeats)")
+        .long("recent")
+        .value_name("N")
+        .takes_value(true)
+}
+
+// Gives up on the command, saying why, for when what it was given won't do.
+fn fail<T, E: Display>(why: E) -> T {
+    eprintln!("Error: {}", why);
+    ::std::process::exit(1)
+}
+
+// The number given as `value` for the option `name`, giving up if it isn't one.
+fn number<T: FromStr>(value: &str, name: &str) -> T {
+    value.parse().unwrap_or_else(|_| fail(format!("{} should be a number", name)))
+}
+
+fn recent_posts(matches: &ArgMatches) -> usize {
+    match matches.value_of("recent") {
+        Some(n) => number(n, "--recent"),
+        None => DEFAULT_RECENT_POSTS,
+    }
+}
+
+fn diagram_colors(matches: &ArgMatches) -> Option<ColorScheme> {
+    if !matches.is_present("diagram") {
+        return None;
+    }
+    match load_style(matches.value_of("style")) {
+        Ok(style) => Some(style.colors),
+        Err(msg) => fail(msg),
+    }
+}
+
 fn main() {
     let matches = App::new("Last Layer Algs")
         .version("0.1")
         .author("Justin Jaffray")
         .about("Generates last layers")
+        .arg(diagram_arg())
+        .arg(style_arg())
+        .arg(format_arg())
+        .arg(case_names_arg("with --format, name the cases from this file of name,alg lines, as `sheet` reads"))
+        .arg(Arg::with_name("count")
+             .help("stop after this many algs, rather than carrying on forever")
+             .long("count")
+             .value_name("N")
+             .takes_value(true))
         .subcommand(SubCommand::with_name("following")
                     .about("Prints out the alg following the given one")
                     .arg(Arg::with_name("alg")
                          .help("the algorithm to follow")
                          .index(1)
-                         .required(true)))
+                         .required(true))
+                    .arg(diagram_arg())
+                    .arg(style_arg())
+                    .arg(format_arg())
+                    .arg(case_names_arg("with --format, name the cases from this file of name,alg lines, as `sheet` reads")))
+        .subcommand(SubCommand::with_name("render")
+                    .about("Draws an image of the case the given alg solves, or the state a scramble leaves")
+                    .arg(Arg::with_name("alg")
+                         .help("the algorithm to draw")
+                         .index(1)
+                         .required(true))
+                    .arg(Arg::with_name("output")
+                         .help("the file to write the image to")
+                         .long("output")
+                         .short("o")
+                         .takes_value(true))
+                    .arg(Arg::with_name("view")
+                         .help("the last layer from above, a net of the whole cube, or an isometric view of it")
+                         .long("view")
+                         .takes_value(true)
+                         .possible_values(&["top", "net", "iso"]))
+                    .arg(Arg::with_name("scramble")
+                         .help("draw the state the alg leaves the cube in, rather than the case it solves")
+                         .long("scramble"))
+                    .arg(Arg::with_name("corner-arrows")
+                         .help("draw arrows showing how the corners cycle")
+                         .long("corner-arrows"))
+                    .arg(Arg::with_name("edge-arrows")
+                         .help("draw arrows showing how the edges cycle")
+                         .long("edge-arrows"))
+                    .arg(Arg::with_name("stickering")
+                         .help("which stickers to show the colours of")
+                         .long("stickering")
+                         .takes_value(true)
+                         .possible_values(&["full", "oll", "coll", "zbll"]))
+                    .arg(style_arg()))
+        .subcommand(SubCommand::with_name("animate")
+                    .about("Makes a GIF of the given alg solving its case")
+                    .arg(Arg::with_name("alg")
+                         .help("the algorithm to animate")
+                         .index(1)
+                         .required(true))
+                    .arg(Arg::with_name("output")
+                         .help("the file to write the GIF to")
+                         .long("output")
+                         .short("o")
+                         .takes_value(true))
+                    .arg(Arg::with_name("frames-per-move")
+                         .help("how many frames to draw each move with, 1 means no partial turns")
+                         .long("frames-per-move")
+                         .takes_value(true))
+                    .arg(Arg::with_name("delay")
+                         .help("how long each frame is shown for, in hundredths of a second")
+                         .long("delay")
+                         .takes_value(true))
+                    .arg(Arg::with_name("frames-dir")
+                         .help("also keep each frame as a PNG in this directory")
+                         .long("frames-dir")
+                         .takes_value(true))
+                    .arg(style_arg()))
+        .subcommand(SubCommand::with_name("sheet")
+                    .about("Makes a printable sheet of cases, with their algs")
+                    .arg(Arg::with_name("algs")
+                         .help("file of algs, one per line and optionally preceded by a name and a comma")
+                         .index(1))
+                    .arg(Arg::with_name("from")
+                         .help("without a file, use the algs following this one")
+                         .long("from")
+                         .takes_value(true))
+                    .arg(Arg::with_name("count")
+                         .help("without a file, how many algs to use")
+                         .long("count")
+                         .takes_value(true))
+                    .arg(Arg::with_name("output")
+                         .help("the PDF to write, or if it ends in .png, the prefix for a PNG per page")
+                         .long("output")
+                         .short("o")
+                         .takes_value(true))
+                    .arg(Arg::with_name("columns")
+                         .help("cases across each page")
+                         .long("columns")
+                         .takes_value(true))
+                    .arg(Arg::with_name("rows")
+                         .help("cases down each page")
+                         .long("rows")
+                         .takes_value(true))
+                    .arg(Arg::with_name("stickering")
+                         .help("which stickers to show the colours of")
+                         .long("stickering")
+                         .takes_value(true)
+                         .possible_values(&["full", "oll", "coll", "zbll"]))
+                    .arg(style_arg()))
+        .subcommand(SubCommand::with_name("export")
+                    .about("Adds algs to an SQLite database, with the cases they solve")
+                    .arg(Arg::with_name("database")
+                         .help("the database file, which is made if it doesn't exist")
+                         .index(1)
+                         .required(true))
+                    .arg(Arg::with_name("from")
+                         .help("add the algs following this one, rather than those following the last one added")
+                         .long("from")
+                         .takes_value(true))
+                    .arg(Arg::with_name("count")
+                         .help("stop after this many algs")
+                         .long("count")
+                         .value_name("N")
+                         .takes_value(true))
+                    .arg(Arg::with_name("max-length")
+                         .help("stop once the algs get longer than this many moves")
+                         .long("max-length")
+                         .value_name("N")
+                         .takes_value(true))
+                    .arg(case_names_arg("name the cases from this file of name,alg lines, as `sheet` reads")))
+        .subcommand(SubCommand::with_name("import")
+                    .about("Checks a file of algs, saying what case each solves and what's wrong with any of them")
+                    .arg(Arg::with_name("algs")
+                         .help("file of algs, one per line or CSV with names")
+                         .index(1)
+                         .required(true))
+                    .arg(Arg::with_name("database")
+                         .help("add the last layer algs to this SQLite database, as `export` does")
+                         .long("database")
+                         .takes_value(true))
+                    .arg(case_names_arg("check the labels against the names in this file of name,alg lines, as `sheet` reads")))
+        .subcommand(SubCommand::with_name("verify")
+                    .about("Says whether an alg solves a case, and with which U turns before and after it")
+                    .arg(Arg::with_name("alg")
+                         .help("the algorithm to check")
+                         .index(1)
+                         .required(true))
+                    .arg(Arg::with_name("case")
+                         .help("an alg setting up the case, e.g. the inverse of one that solves it")
+                         .long("case")
+                         .value_name("SETUP")
+                         .takes_value(true)
+                         .required(true))
+                    .arg(style_arg()))
+        .subcommand(SubCommand::with_name("count")
+                    .about("Counts the algs and cases of each length, without printing the algs")
+                    .arg(Arg::with_name("max-length")
+                         .help("the longest algs to count")
+                         .long("max-length")
+                         .value_name("N")
+                         .takes_value(true)
+                         .required(true))
+                    .arg(Arg::with_name("min-length")
+                         .help("the shortest length to print counts for, shorter ones still count towards the totals")
+                         .long("min-length")
+                         .value_name("N")
+                         .takes_value(true)))
+        .subcommand(SubCommand::with_name("optimal")
+                    .about("Finds the shortest algs for every case, and how many cases need each number of moves")
+                    .arg(Arg::with_name("subset")
+                         .help("only solve cases from this subset, as all of them take a day or more")
+                         .long("subset")
+                         .takes_value(true)
+                         .possible_values(&["PLL", "OLL", "ZBLL", "1LLL"]))
+                    .arg(case_names_arg("name the cases from this file of name,alg lines, as `sheet` reads")))
+        .subcommand(SubCommand::with_name("scramble")
+                    .about("Prints scrambles for random states, for practising")
+                    .arg(Arg::with_name("ll")
+                         .help("scramble the last layer, with the rest of the cube solved")
+                         .long("ll")
+                         .required(true))
+                    .arg(Arg::with_name("count")
+                         .help("how many scrambles to print")
+                         .long("count")
+                         .value_name("N")
+                         .takes_value(true))
+                    .arg(Arg::with_name("subset")
+                         .help("only scramble cases from this subset")
+                         .long("subset")
+                         .takes_value(true)
+                         .possible_values(&["PLL", "OLL", "ZBLL", "1LLL"]))
+                    .arg(Arg::with_name("max-alg-length")
+                         .help("make scrambles out of algs up to this many moves long, longer ones give shorter scrambles but take longer to start")
+                         .long("max-alg-length")
+                         .value_name("N")
+                         .takes_value(true))
+                    .arg(diagram_arg())
+                    .arg(style_arg()))
         .subcommand(SubCommand::with_name("tweet")
-                    .about("Tweet out the next alg"))
+                    .about("Tweet out the next alg")
+                    .arg(animate_arg())
+                    .arg(recent_arg())
+                    .arg(Arg::with_name("dry-run")
+                         .help("write what would be posted to this directory rather than posting it")
+                         .long("dry-run")
+                         .value_name("DIR")
+                         .takes_value(true))
+                    .arg(style_arg()))
+        .subcommand(SubCommand::with_name("serve")
+                    .about("Keeps running, posting the next alg on a schedule")
+                    .arg(Arg::with_name("schedule")
+                         .help("when to post, as a cron expression in UTC, e.g. \"0 12 * * *\" for noon every day")
+                         .long("schedule")
+                         .value_name("CRON")
+                         .takes_value(true)
+                         .required(true))
+                    .arg(Arg::with_name("jitter")
+                         .help("post up to this many seconds after the scheduled time, picked at random each time")
+                         .long("jitter")
+                         .value_name("SECS")
+                         .takes_value(true))
+                    .arg(animate_arg())
+                    .arg(recent_arg())
+                    .arg(style_arg()))
         .get_matches();
 
     if let Some(matches) = matches.subcommand_matches("following") {
         if let Some(alg) = matches.value_of("alg") {
-            match alg_following(alg) {
-                Ok(next) => println!("{}", next),
-                Err(msg) => panic!("Error: {}", msg),
+            let next = match alg_following(alg) {
+                Ok(next) => next,
+                Err(msg) => fail(msg),
+            };
+            let printed = match record_format(matches) {
+                Ok(Some(format)) => case_names_from(matches)
+                    .and_then(|names| print_records(Some(next).into_iter(), format, Some(1), &names)),
+                Ok(None) => Ok(print_alg(&next, diagram_colors(matches).as_ref())),
+                Err(why) => Err(From::from(why)),
+            };
+            if let Err(msg) = printed {
+                fail(msg)
             }
         }
-    } else if let Some(_) = matches.subcommand_matches("tweet") {
-        match prepare_tweet() {
-            Err(why) => panic!("Error: {}", why),
-            Ok(()) => {}
+    } else if let Some(matches) = matches.subcommand_matches("render") {
+        let stickering = match matches.value_of("stickering") {
+            Some(s) => Stickering::from_str(s).unwrap_or_else(fail),
+            None => Stickering::default(),
+        };
+        let style = match load_style(matches.value_of("style")) {
+            Ok(style) => style,
+            Err(msg) => fail(msg),
+        };
+        let options = ImageOptions {
+            corner_arrows: matches.is_present("corner-arrows"),
+            edge_arrows: matches.is_present("edge-arrows"),
+            stickering: stickering,
+            style: style,
+        };
+        let output = matches.value_of("output").unwrap_or(IMAGE_FNAME);
+        let view = matches.value_of("view").unwrap_or("top");
+        if let Some(alg) = matches.value_of("alg") {
+            if let Err(msg) = render(alg, output, &options, view, matches.is_present("scramble")) {
+                fail(msg)
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("animate") {
+        let style = match load_style(matches.value_of("style")) {
+            Ok(style) => style,
+            Err(msg) => fail(msg),
+        };
+        let options = ImageOptions { style: style, ..ImageOptions::default() };
+        let mut animation = AnimationOptions::default();
+        if let Some(n) = matches.value_of("frames-per-move") {
+            animation.frames_per_move = number(n, "--frames-per-move");
+        }
+        if let Some(n) = matches.value_of("delay") {
+            animation.frame_delay = number(n, "--delay");
+        }
+        animation.frames_dir = matches.value_of("frames-dir").map(String::from);
+        let output = matches.value_of("output").unwrap_or(ANIMATION_FNAME);
+        if let Some(alg) = matches.value_of("alg") {
+            if let Err(msg) = animate(alg, output, &options, &animation) {
+                fail(msg)
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("sheet") {
+        let style = match load_style(matches.value_of("style")) {
+            Ok(style) => style,
+            Err(msg) => fail(msg),
+        };
+        let stickering = match matches.value_of("stickering") {
+            Some(s) => Stickering::from_str(s).unwrap_or_else(fail),
+            None => Stickering::default(),
+        };
+        let options = ImageOptions { style: style, stickering: stickering, ..ImageOptions::default() };
+        let default_layout = SheetLayout::default();
+        let columns = matches.value_of("columns").map_or(default_layout.columns, |n| number(n, "--columns"));
+        let rows = matches.value_of("rows").map_or(default_layout.rows, |n| number(n, "--rows"));
+        let layout = match SheetLayout::new(columns, rows) {
+            Ok(layout) => layout,
+            Err(msg) => fail(msg),
+        };
+        let count = number(matches.value_of("count").unwrap_or("30"), "--count");
+        let from = matches.value_of("from").unwrap_or("");
+        let output = matches.value_of("output").unwrap_or(SHEET_FNAME);
+        if let Err(msg) = make_sheet(matches.value_of("algs"), from, count, output, &layout, &options) {
+            fail(msg)
+        }
+    } else if let Some(matches) = matches.subcommand_matches("export") {
+        let count = matches.value_of("count").map(|n| number(n, "--count"));
+        let max_length = matches.value_of("max-length").map(|n| number(n, "--max-length"));
+        let exported = case_names_from(matches)
+            .and_then(|names| export(matches.value_of("database").unwrap(), matches.value_of("from"), count, max_length, &names));
+        if let Err(why) = exported {
+            eprintln!("Error: {}", why);
+            ::std::process::exit(1);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("import") {
+        let imported = case_names_from(matches)
+            .and_then(|names| import_algs(matches.value_of("algs").unwrap(), matches.value_of("database"), &names));
+        match imported {
+            Ok(0) => (),
+            Ok(_) => ::std::process::exit(1),
+            Err(why) => {
+                eprintln!("Error: {}", why);
+                ::std::process::exit(1);
+            },
+        }
+    } else if let Some(matches) = matches.subcommand_matches("verify") {
+        let style = match load_style(matches.value_of("style")) {
+            Ok(style) => style,
+            Err(msg) => fail(msg),
+        };
+        match verify(matches.value_of("alg").unwrap(), matches.value_of("case").unwrap(), &style.colors) {
+            Ok(true) => (),
+            Ok(false) => ::std::process::exit(1),
+            Err(why) => {
+                eprintln!("Error: {}", why);
+                ::std::process::exit(1);
+            },
+        }
+    } else if let Some(matches) = matches.subcommand_matches("count") {
+        let max_length = number(matches.value_of("max-length").unwrap(), "--max-length");
+        let min_length = number(matches.value_of("min-length").unwrap_or("0"), "--min-length");
+        if let Err(why) = count(min_length, max_length) {
+            eprintln!("Error: {}", why);
+            ::std::process::exit(1);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("optimal") {
+        if let Err(why) = case_names_from(matches).and_then(|names| optimal(matches.value_of("subset"), &names)) {
+            eprintln!("Error: {}", why);
+            ::std::process::exit(1);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("scramble") {
+        let style = match load_style(matches.value_of("style")) {
+            Ok(style) => style,
+            Err(msg) => fail(msg),
+        };
+        let diagram = if matches.is_present("diagram") { Some(&style.colors) } else { None };
+        let count = number(matches.value_of("count").unwrap_or("1"), "--count");
+        let max_alg_length = number(matches.value_of("max-alg-length").unwrap_or("9"), "--max-alg-length");
+        if let Err(why) = scramble(count, matches.value_of("subset"), max_alg_length, diagram) {
+            eprintln!("Error: {}", why);
+            ::std::process::exit(1);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("tweet") {
+        let recent = recent_posts(matches);
+        if let Err(why) = prepare_tweet(matches.value_of("style"), matches.is_present("animate"), matches.value_of("dry-run"), recent) {
+            // whatever didn't get posted is still pending, and the next run picks it up
+            eprintln!("Error: {}", why);
+            ::std::process::exit(1);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("serve") {
+        let schedule = match Schedule::from_str(matches.value_of("schedule").unwrap()) {
+            Ok(schedule) => schedule,
+            Err(msg) => fail(msg),
+        };
+        let jitter = number(matches.value_of("jitter").unwrap_or("0"), "--jitter");
+        let mut poster = ScheduledPoster {
+            style_filename: matches.value_of("style"),
+            animate: matches.is_present("animate"),
+            recent: recent_posts(matches),
+            prepared: None,
+        };
+        if let Err(why) = daemon::run(&SystemClock, &schedule, jitter, &mut poster) {
+            eprintln!("Error: {}", why);
+            ::std::process::exit(1);
         }
     } else {
-        let mut it = AlgorithmIterator::new();
-        // for _ in 0..300 {
-        loop {
-            println!("{}", it.next().unwrap());
+        let count = matches.value_of("count").map(|n| number(n, "--count"));
+        let format = match record_format(&matches) {
+            Ok(format) => format,
+            Err(why) => {
+                eprintln!("Error: {}", why);
+                ::std::process::exit(1);
+            },
+        };
+        if let Some(format) = format {
+            let printed = case_names_from(&matches).and_then(|names| print_records(AlgorithmIterator::new(), format, count, &names));
+            if let Err(why) = printed {
+                eprintln!("Error: {}", why);
+                ::std::process::exit(1);
+            }
+            return;
+        }
+        let diagram = diagram_colors(&matches);
+        for alg in AlgorithmIterator::new().take(count.unwrap_or(usize::max_value())) {
+            print_alg(&alg, diagram.as_ref());
         }
     }
 }
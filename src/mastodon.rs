@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+use rustc_serialize::json::{Json, Object};
+use http::{self, Multipart, Request};
+use publisher::{Publisher, base_name, media_gone_if, read_file, required_string};
+
+// How long to wait for the server to finish processing an upload (GIFs get turned into video)
+// before giving up on it.
+const PROCESSING_POLLS: usize = 30;
+const PROCESSING_POLL_INTERVAL_SECS: u64 = 2;
+
+pub struct Mastodon {
+    // e.g. https://mastodon.social
+    instance: String,
+    access_token: String,
+}
+
+impl Mastodon {
+    pub fn from_config(section: &Object) -> Result<Self, Box<Error>> {
+        let instance = required_string(section, "mastodon", "instance")?;
+        Ok(Mastodon {
+            instance: String::from(instance.trim_right_matches('/')),
+            access_token: required_string(section, "mastodon", "access_token")?,
+        })
+    }
+
+    fn authorized(&self, method: &'static str, path: &str) -> Request {
+        Request::new(method, format!("{}{}", self.instance, path).as_str())
+            .header("Authorization", format!("Bearer {}", self.access_token).as_str())
+    }
+}
+
+impl Publisher for Mastodon {
+    fn name(&self) -> &str {
+        "mastodon"
+    }
+
+    fn upload_media(&self, filename: &str) -> Result<String, Box<Error>> {
+        let form = Multipart::new().file("file", base_name(filename).as_str(), &read_file(filename)?);
+        let response = http::send(&self.authorized("POST", "/api/v2/media").multipart(form).repeatable())?;
+        let id = response.field(&["id"])?;
+
+        // A 202 means it's still being processed, and it can't be attached to a post until
+        // that's done
+        if response.status == 202 {
+            for _ in 0..PROCESSING_POLLS {
+                thread::sleep(Duration::from_secs(PROCESSING_POLL_INTERVAL_SECS));
+                let poll = http::send(&self.authorized("GET", format!("/api/v1/media/{}", id).as_str()))?;
+                if poll.status == 200 {
+                    return Ok(id);
+                }
+            }
+            return Err(From::from(format!("Mastodon was still processing media {}", id)));
+        }
+        Ok(id)
+    }
+
+    fn set_alt_text(&self, media_id: &str, alt_text: &str) -> Result<(), Box<Error>> {
+        let mut body = Object::new();
+        body.insert(String::from("description"), Json::String(String::from(alt_text)));
+        http::send(&self.authorized("PUT", format!("/api/v1/media/{}", media_id).as_str()).json(&Json::Object(body)))?;
+        Ok(())
+    }
+
+    fn post_status(&self, status: &str, media_id: &str) -> Result<String, Box<Error>> {
+        let mut body = Object::new();
+        body.insert(String::from("status"), Json::String(String::from(status)));
+        body.insert(String::from("media_ids"), Json::Array(vec![Json::String(String::from(media_id))]));
+
+        // Mastodon won't post a second status with the same key, and the media id is one that stays
+        // the same if this gets tried again, even on a later run
+        let request = self.authorized("POST", "/api/v1/statuses")
+            .header("Idempotency-Key", media_id)
+            .json(&Json::Object(body))
+            .repeatable();
+        // an upload that's been cleared away, or already went out with another post, gets a 422
+        // saying so
+        let response = http::send(&request).map_err(|err| media_gone_if(err, 422, "not found"))?;
+        response.field(&["id"])
+    }
+}
+
+#[test]
+fn posts_to_mastodon_api() {
+    use mock_server::{MockServer, temp_file};
+
+    let server = MockServer::start(vec![
+        (200, r#"{"id": "22", "type": "image"}"#),
+        (200, r#"{"id": "22", "type": "image", "description": "A last layer case"}"#),
+        (200, r#"{"id": "109", "url": "https://mastodon.example/@bot/109"}"#),
+    ]);
+    let mastodon = Mastodon { instance: server.url.clone(), access_token: String::from("token") };
+    let image = temp_file("mastodon.png", b"PNG");
+    let media_id = mastodon.upload_media(image.as_str()).unwrap();
+    mastodon.set_alt_text(media_id.as_str(), "A last layer case").unwrap();
+    assert_eq!(mastodon.post_status("R U R' U R U2 R'", media_id.as_str()).unwrap(), "109");
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/api/v2/media");
+    assert_eq!(requests[0].header("authorization"), Some("Bearer token"));
+    assert_eq!((requests[1].method.as_str(), requests[1].path.as_str()), ("PUT", "/api/v1/media/22"));
+    assert_eq!(Json::from_str(requests[1].body_string().as_str()).unwrap(),
+               Json::from_str(r#"{"description": "A last layer case"}"#).unwrap());
+    assert_eq!(requests[2].path, "/api/v1/statuses");
+    assert_eq!(requests[2].header("idempotency-key"), Some("22"));
+    assert_eq!(Json::from_str(requests[2].body_string().as_str()).unwrap(),
+               Json::from_str(r#"{"status": "R U R' U R U2 R'", "media_ids": ["22"]}"#).unwrap());
+
+    ::std::fs::remove_file(image).unwrap();
+}
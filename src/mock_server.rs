@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+use oauth::{self, Token};
+
+// A stand-in for the posting APIs in tests. It answers requests with canned responses, in
+// order, and keeps hold of what was sent so the test can check it afterwards.
+
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    // names are lowercased
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref v)| v.as_str())
+    }
+
+    pub fn body_string(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+pub struct MockServer {
+    pub url: String,
+    handle: JoinHandle<Vec<RecordedRequest>>,
+}
+
+fn read_request(stream: &mut ::std::net::TcpStream) -> RecordedRequest {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let parts: Vec<String> = line.split_whitespace().map(String::from).collect();
+
+    let mut headers = vec![];
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_right();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(idx) = line.find(':') {
+            headers.push((line[..idx].trim().to_lowercase(), String::from(line[idx + 1..].trim())));
+        }
+    }
+
+    let mut req = RecordedRequest { method: parts[0].clone(), path: parts[1].clone(), headers: headers, body: vec![] };
+    if req.header("expect") == Some("100-continue") {
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+    }
+    let length = req.header("content-length").map(|l| l.parse().unwrap()).unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    req.body = body;
+    req
+}
+
+impl MockServer {
+    // Answers one request with each of `responses`, a status and JSON body, then stops.
+    pub fn start(responses: Vec<(u32, &'static str)>) -> Self {
+        Self::start_with_headers(responses.into_iter().map(|(status, body)| (status, vec![], body)).collect())
+    }
+
+    // The same, with some extra headers on each response.
+    pub fn start_with_headers(responses: Vec<(u32, Vec<(&'static str, &'static str)>, &'static str)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = vec![];
+            for (status, headers, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                requests.push(read_request(&mut stream));
+                let extra: String = headers.iter().map(|&(name, value)| format!("{}: {}\r\n", name, value)).collect();
+                write!(stream, "HTTP/1.1 {} Whatever\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+                       status, body.len(), extra, body).unwrap();
+            }
+            requests
+        });
+        MockServer { url: url, handle: handle }
+    }
+
+    // Waits for all the responses to have been used up.
+    pub fn requests(self) -> Vec<RecordedRequest> {
+        self.handle.join().unwrap()
+    }
+}
+
+fn unescape(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut result = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            result.push(u8::from_str_radix(&s[i + 1..i + 3], 16).unwrap());
+            i += 3;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(result).unwrap()
+}
+
+// Whether the request carries a valid OAuth signature for `url`, made with these tokens.
+pub fn has_valid_oauth(req: &RecordedRequest, url: &str, consumer: &Token, access: &Token) -> bool {
+    let header = match req.header("authorization") {
+        Some(h) if h.starts_with("OAuth ") => h,
+        _ => return false,
+    };
+    let fields: HashMap<String, String> = header["OAuth ".len()..].split(", ")
+        .filter_map(|f| f.find('=').map(|idx| (String::from(&f[..idx]), unescape(f[idx + 1..].trim_matches('"')))))
+        .collect();
+    let (nonce, timestamp) = match (fields.get("oauth_nonce"), fields.get("oauth_timestamp").and_then(|t| t.parse().ok())) {
+        (Some(n), Some(t)) => (n, t),
+        _ => return false,
+    };
+    header == oauth::header_with(req.method.as_str(), url, &[], consumer, access, nonce, timestamp)
+}
+
+pub fn temp_file(name: &str, contents: &[u8]) -> String {
+    let path = ::std::env::temp_dir().join(format!("lla-{}-{}", ::std::process::id(), name));
+    File::create(&path).unwrap().write_all(contents).unwrap();
+    path.to_string_lossy().into_owned()
+}
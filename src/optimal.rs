@@ -0,0 +1,523 @@
+use std::cmp::max;
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+use algorithm::Algorithm;
+use cases::{key_of_case, subset_of_case, symmetry_keys};
+use corner_orientation::CO_TRANSITIONS;
+use corner_permutation::TRANSITIONS;
+use cubestate::CubeState;
+use edge_orientation::move_edge;
+use f2l_cubestate::F2LCubeState;
+use generator::{Face, Generator, Modifier, GENERATORS};
+
+// The shortest algs for every last layer case, in both the half and the quarter turn metric, and
+// how many there are. Each case is solved on its own by iterative deepening: a depth first search
+// through every sequence of moves of a given length, one longer each time until some solve it.
+// Before going any deeper, the search checks the pruning tables the enumeration uses, to see
+// whether the F2L can be got back in the moves left, and tables of how far the corners and each
+// half of the edges are from solved, to see whether they can. Those take a while to make, and
+// the longer cases take a while to solve, anything from a moment to several minutes each.
+
+// Algs which, along with each other, reach every state of the last layer.
+const GENERATING_ALGS: [&'static str; 4] = [
+    "U",
+    "R U R' U R U2 R'",
+    "F R U R' U' F'",
+    "R U R' U' R' F R2 U' R' U' R U R' F'",
+];
+
+// Every state the last layer can be in with the rest of the cube solved, solved included, along
+// with the same state as the search keeps it.
+fn ll_state_pairs() -> Vec<(CubeState, F2LCubeState)> {
+    let algs: Vec<Algorithm> = GENERATING_ALGS.iter().map(|s| Algorithm::from_str(s).unwrap()).collect();
+    let effects: Vec<CubeState> = algs.iter().map(|a| a.cube()).collect();
+    let mut seen = HashSet::new();
+    seen.insert(CubeState::solved().state.to_vec());
+    let mut states = vec![(CubeState::solved(), F2LCubeState::new())];
+    let mut i = 0;
+    while i < states.len() {
+        for (alg, effect) in algs.iter().zip(effects.iter()) {
+            let next = states[i].0.apply(effect);
+            if seen.insert(next.state.to_vec()) {
+                let f2l_state = alg.moves.iter().fold(states[i].1, |s, &m| s.apply(m));
+                states.push((next, f2l_state));
+            }
+        }
+        i += 1;
+    }
+    states
+}
+
+// Every state the last layer can be in with the rest of the cube solved, solved included. There
+// are 62208 of them: 4!·4!/2 permutations, as the corners' and the edges' are both odd or both
+// even, times 3^3 ways to twist the corners and 2^3 to flip the edges.
+pub fn ll_states() -> Vec<CubeState> {
+    ll_state_pairs().into_iter().map(|(state, _)| state).collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    // every turn of a face is one move
+    HalfTurn,
+    // turning a face twice is two
+    QuarterTurn,
+}
+
+impl Metric {
+    fn cost(&self, g: &Generator) -> usize {
+        match (*self, g.components().1) {
+            (Metric::QuarterTurn, Modifier::Twice) => 2,
+            _ => 1,
+        }
+    }
+}
+
+const CORNER_STATES: usize = 40320 * 2187;
+// The edges are split into two groups of six, the U layer's with two of the middle layer's and
+// the D layer's with the other two. Each group can be in 12·11·10·9·8·7 positions, with 2^6 ways
+// to be flipped.
+const EDGE_STATES: usize = 665280 * 64;
+// the group each edge is in, and which of the group it is
+const EDGE_GROUPS: [(usize, usize); 12] = [
+    (0, 0), (0, 1), (0, 2), (0, 3), (1, 0), (1, 1), (1, 2), (1, 3), (0, 4), (0, 5), (1, 4), (1, 5),
+];
+
+fn corner_index(state: &F2LCubeState) -> usize {
+    let (cp, co) = state.corners();
+    cp * 2187 + co
+}
+
+fn move_corners(index: usize, m: usize) -> usize {
+    TRANSITIONS[index / 2187][m] * 2187 + CO_TRANSITIONS[index % 2187][m]
+}
+
+// Numbers the positions by how many free positions come before each, as a position can't have
+// two edges in it.
+fn edge_index(positions: &[usize; 6], flips: usize) -> usize {
+    let mut used: u32 = 0;
+    let mut index = 0;
+    for (i, &p) in positions.iter().enumerate() {
+        index = index * (12 - i) + (((1 << p) - 1) & !used).count_ones() as usize;
+        used |= 1 << p;
+    }
+    index * 64 + flips
+}
+
+fn edge_positions(index: usize) -> [usize; 6] {
+    let mut free_before = [0; 6];
+    let mut rest = index;
+    for i in (0..6).rev() {
+        free_before[i] = rest % (12 - i);
+        rest /= 12 - i;
+    }
+    let mut used: u32 = 0;
+    let mut positions = [0; 6];
+    for i in 0..6 {
+        positions[i] = (0..12).filter(|&q| used & (1 << q) == 0).nth(free_before[i]).unwrap();
+        used |= 1 << positions[i];
+    }
+    positions
+}
+
+fn edge_indices(state: &F2LCubeState) -> [usize; 2] {
+    let mut positions = [[0; 6]; 2];
+    let mut flips = [0; 2];
+    for position in 0..12 {
+        let (edge, flip) = state.edge_at(position);
+        let (group, i) = EDGE_GROUPS[edge];
+        positions[group][i] = position;
+        flips[group] |= (flip as usize) << i;
+    }
+    [edge_index(&positions[0], flips[0]), edge_index(&positions[1], flips[1])]
+}
+
+// Where each move takes a group of edges from each of their positions, and which of them it flips.
+fn edge_transitions() -> (Vec<[u32; 18]>, Vec<[u8; 18]>) {
+    let mut positions = vec![[0; 18]; EDGE_STATES / 64];
+    let mut flips = vec![[0; 18]; EDGE_STATES / 64];
+    for index in 0..EDGE_STATES / 64 {
+        let before = edge_positions(index);
+        for m in 0..18 {
+            let mut after = [0; 6];
+            for i in 0..6 {
+                let (to, flip) = move_edge(m, before[i]);
+                after[i] = to;
+                flips[index][m] |= flip << i;
+            }
+            positions[index][m] = (edge_index(&after, 0) / 64) as u32;
+        }
+    }
+    (positions, flips)
+}
+
+// How many moves each of `states` states is from the nearest of `goals`, found a distance at a
+// time from those known to be one move closer. Every move's inverse is a move costing as much, so
+// it's just as far the other way.
+fn distances<F: Fn(usize, usize) -> usize>(states: usize, goals: &[usize], metric: Metric, next: F) -> Vec<u8> {
+    let mut result = vec![u8::max_value(); states];
+    for &goal in goals {
+        result[goal] = 0;
+    }
+    let mut distance = 0;
+    let mut furthest = 0;
+    while distance <= furthest {
+        for i in 0..states {
+            if result[i] as usize != distance {
+                continue;
+            }
+            for (m, g) in GENERATORS.iter().enumerate() {
+                let next_state = next(i, m);
+                let next_distance = distance + metric.cost(g);
+                if next_distance < result[next_state] as usize {
+                    result[next_state] = next_distance as u8;
+                    furthest = max(furthest, next_distance);
+                }
+            }
+        }
+        distance += 1;
+    }
+    result
+}
+
+// How far the corners, and each group of edges, are from being solved up to a U turn.
+struct DistanceTables {
+    corners: Vec<u8>,
+    edges: Vec<Vec<u8>>,
+}
+
+impl DistanceTables {
+    fn new(metric: Metric) -> Self {
+        let solved = F2LCubeState::new();
+        let mut goals = vec![solved];
+        goals.extend(GENERATORS.iter().filter(|g| g.is_u_move()).map(|&g| solved.apply(g)));
+        let corner_goals: Vec<usize> = goals.iter().map(corner_index).collect();
+        let edge_goals: Vec<[usize; 2]> = goals.iter().map(edge_indices).collect();
+        let (positions, flips) = edge_transitions();
+        let move_edges = |index: usize, m: usize| positions[index / 64][m] as usize * 64 + ((index % 64) ^ flips[index / 64][m] as usize);
+        DistanceTables {
+            corners: distances(CORNER_STATES, &corner_goals, metric, move_corners),
+            edges: (0..2)
+                .map(|group| {
+                    let goals: Vec<usize> = edge_goals.iter().map(|indices| indices[group]).collect();
+                    distances(EDGE_STATES, &goals, metric, move_edges)
+                })
+                .collect(),
+        }
+    }
+
+}
+
+// The fewest moves a state could be solved in.
+trait LowerBound {
+    fn lower_bound(&self, state: &F2LCubeState) -> usize;
+}
+
+impl LowerBound for DistanceTables {
+    fn lower_bound(&self, state: &F2LCubeState) -> usize {
+        let edges = edge_indices(state);
+        (0..2).fold(self.corners[corner_index(state)], |bound, group| max(bound, self.edges[group][edges[group]])) as usize
+    }
+}
+
+lazy_static! {
+    static ref HALF_TURN_TABLES: DistanceTables = DistanceTables::new(Metric::HalfTurn);
+    static ref QUARTER_TURN_TABLES: DistanceTables = DistanceTables::new(Metric::QuarterTurn);
+    // An AUF beforehand is taken care of by searching from each state it could leave. Each alg is
+    // only looked for held so that its first move apart from D turns is an R turn, as held any
+    // other way round it solves the same case.
+    static ref FIRST_MOVES: Vec<&'static Generator> = GENERATORS.iter()
+        .filter(|g| g.is_d_move() || g.face == Face::R)
+        .collect();
+    static ref R_MOVES: Vec<&'static Generator> = Generator::starting_moves();
+}
+
+struct Search<'a> {
+    metric: Metric,
+    bound: &'a LowerBound,
+    moves: Vec<&'static Generator>,
+    solutions: usize,
+    example: Option<String>,
+}
+
+impl<'a> Search<'a> {
+    // An AUF at the end is left off, as it is from any other alg. `U D` comes to the same as
+    // `D U`, which the successors don't allow.
+    fn ends_in_u_move(&self) -> bool {
+        let n = self.moves.len();
+        match self.moves.last() {
+            Some(m) if m.is_u_move() => true,
+            Some(m) if m.is_d_move() => n > 1 && self.moves[n - 2].is_u_move(),
+            _ => false,
+        }
+    }
+
+    fn found(&mut self) {
+        // One starting with R and L turns together, which could be done in either order, turns
+        // up held both ways, so it only counts for half each time.
+        let first = self.moves.iter().position(|m| !m.is_d_move()).unwrap();
+        let starts_with_r_and_l = self.moves.get(first + 1).map(|m| m.face) == Some(Face::L);
+        self.solutions += if starts_with_r_and_l { 1 } else { 2 };
+        if self.example.is_none() {
+            let moves: Vec<String> = self.moves.iter().map(|m| format!("{}", m)).collect();
+            self.example = Some(moves.join(" "));
+        }
+    }
+
+    // Counts the algs solving the state in exactly `moves_left` moves after those so far.
+    fn search(&mut self, state: &F2LCubeState, moves_left: usize) {
+        if moves_left == 0 {
+            if state.solved_up_to_auf() && !self.ends_in_u_move() {
+                self.found();
+            }
+            return;
+        }
+        if state.prunable(moves_left as u16) || self.bound.lower_bound(state) > moves_left {
+            return;
+        }
+        let successors: &[&'static Generator] = match self.moves.last() {
+            None => &FIRST_MOVES,
+            Some(m) if self.moves.len() == 1 && m.is_d_move() => &R_MOVES,
+            Some(m) => m.successors(),
+        };
+        for &g in successors {
+            let cost = self.metric.cost(g);
+            if cost <= moves_left {
+                self.moves.push(g);
+                self.search(&state.apply(*g), moves_left - cost);
+                self.moves.pop();
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Optimum {
+    pub length: usize,
+    // how many algs there are of that length, counting each once whichever way round it's held
+    pub solutions: usize,
+    pub example: String,
+}
+
+// A last layer case, up to AUF.
+pub struct Case {
+    pub key: String,
+    pub subset: &'static str,
+    // the states the case can leave the cube in, one for each AUF beforehand that doesn't come to
+    // the same as another with a different AUF afterwards
+    starts: Vec<F2LCubeState>,
+}
+
+impl Case {
+    // The shortest algs for the case in the metric.
+    pub fn solve(&self, metric: Metric) -> Optimum {
+        match metric {
+            Metric::HalfTurn => self.solve_with(metric, &*HALF_TURN_TABLES),
+            Metric::QuarterTurn => self.solve_with(metric, &*QUARTER_TURN_TABLES),
+        }
+    }
+
+    fn solve_with(&self, metric: Metric, bound: &LowerBound) -> Optimum {
+        let mut search = Search { metric: metric, bound: bound, moves: vec![], solutions: 0, example: None };
+        let mut length = self.starts.iter().map(|s| bound.lower_bound(s)).min().unwrap();
+        loop {
+            for start in &self.starts {
+                search.search(start, length);
+            }
+            if let Some(example) = search.example {
+                return Optimum { length: length, solutions: search.solutions / 2, example: example };
+            }
+            length += 1;
+        }
+    }
+}
+
+// Every case, apart from those needing only an AUF, in order of their keys.
+pub fn ll_cases() -> Vec<Case> {
+    let u_turns: Vec<CubeState> = ["U", "U2", "U'"].iter().map(|s| Algorithm::from_str(s).unwrap().cube()).collect();
+    let solved_key = key_of_case(&CubeState::solved());
+    let mut by_key: BTreeMap<String, Vec<(CubeState, F2LCubeState)>> = BTreeMap::new();
+    for (state, f2l_state) in ll_state_pairs() {
+        let key = key_of_case(&state);
+        if key != solved_key {
+            by_key.entry(key).or_insert(vec![]).push((state, f2l_state));
+        }
+    }
+    by_key.into_iter()
+        .map(|(key, states)| {
+            let subset = subset_of_case(&states[0].0);
+            let mut seen = HashSet::new();
+            let mut starts = vec![];
+            for (state, f2l_state) in states {
+                if u_turns.iter().all(|u| !seen.contains(&u.apply(&state).state[..])) {
+                    seen.insert(state.state.to_vec());
+                    starts.push(f2l_state);
+                }
+            }
+            Case { key: key, subset: subset, starts: starts }
+        })
+        .collect()
+}
+
+pub struct CaseOptimum {
+    pub key: String,
+    pub subset: &'static str,
+    // how many cases the row is for: the case itself, and its mirror and inverse, which have as
+    // many algs just as long
+    pub cases: usize,
+    // in the half turn metric
+    pub htm: Optimum,
+    // in the quarter turn metric
+    pub qtm: Optimum,
+}
+
+pub struct OptimalTable {
+    pub cases: Vec<CaseOptimum>,
+}
+
+impl OptimalTable {
+    // Solves every case, or every case in `subset` if it's given, calling `solved` with each as
+    // it's done. Mirrors and inverses of a case already done are left out.
+    pub fn new<F: FnMut(&CaseOptimum)>(subset: Option<&str>, mut solved: F) -> Self {
+        let mut done = HashSet::new();
+        let mut cases = vec![];
+        for case in ll_cases() {
+            if done.contains(&case.key) || subset.is_some() && subset != Some(case.subset) {
+                continue;
+            }
+            let htm = case.solve(Metric::HalfTurn);
+            let qtm = case.solve(Metric::QuarterTurn);
+            let keys: HashSet<String> = symmetry_keys(&Algorithm::from_str(htm.example.as_str()).unwrap()).into_iter().collect();
+            let row = CaseOptimum { key: case.key, subset: case.subset, cases: keys.len(), htm: htm, qtm: qtm };
+            solved(&row);
+            done.extend(keys);
+            cases.push(row);
+        }
+        OptimalTable { cases: cases }
+    }
+
+    // How many cases need each number of moves, mirrors and inverses included.
+    pub fn histogram<F: Fn(&CaseOptimum) -> &Optimum>(&self, optimum: F) -> Vec<(usize, usize)> {
+        let mut counts: Vec<usize> = vec![];
+        for case in &self.cases {
+            let length = optimum(case).length;
+            if counts.len() <= length {
+                counts.resize(length + 1, 0);
+            }
+            counts[length] += case.cases;
+        }
+        counts.into_iter().enumerate().filter(|&(_, n)| n > 0).collect()
+    }
+}
+
+#[test]
+fn finds_every_ll_state() {
+    let states = ll_states();
+    assert_eq!(states.len(), 62208);
+    let cases = ll_cases();
+    assert_eq!(cases.len(), 3915);
+    assert_eq!(cases.iter().filter(|c| c.subset == "PLL").count(), 21);
+    // the U layer looks the same turned any way round
+    let h_perm = key_of_case(&Algorithm::from_str("R2 U2 R U2 R2 U2 R2 U2 R U2 R2").unwrap().cube());
+    assert_eq!(cases.iter().find(|c| c.key == h_perm).unwrap().starts.len(), 1);
+}
+
+#[test]
+fn numbers_edge_positions() {
+    for index in 0..EDGE_STATES / 64 {
+        assert_eq!(edge_index(&edge_positions(index), 5), index * 64 + 5);
+    }
+}
+
+#[test]
+fn finds_distances() {
+    // six states in a ring, where every move goes one way or the other round it
+    let around = |i: usize, m: usize| if m % 2 == 1 { (i + 5) % 6 } else { (i + 1) % 6 };
+    assert_eq!(distances(6, &[0], Metric::HalfTurn, around), vec![0, 1, 2, 3, 2, 1]);
+    assert_eq!(distances(6, &[0, 3], Metric::QuarterTurn, around), vec![0, 1, 1, 0, 1, 1]);
+    // half turns go twice as far in the quarter turn metric
+    let half_turns_skip = |i: usize, m: usize| if GENERATORS[m].components().1 == Modifier::Twice { (i + 2) % 6 } else { i };
+    assert_eq!(distances(6, &[0], Metric::HalfTurn, half_turns_skip), vec![0, 255, 1, 255, 2, 255]);
+    assert_eq!(distances(6, &[0], Metric::QuarterTurn, half_turns_skip), vec![0, 255, 2, 255, 4, 255]);
+}
+
+// How far every state within `depth` moves of solved (up to a U turn) is, with one more than that
+// as the bound for the rest. It stands in for the full tables, which take too long to make here.
+#[cfg(test)]
+struct NearSolved {
+    distances: ::std::collections::HashMap<(usize, [usize; 2]), usize>,
+    depth: usize,
+}
+
+#[cfg(test)]
+impl NearSolved {
+    fn new(metric: Metric, depth: usize) -> Self {
+        let key = |state: &F2LCubeState| (corner_index(state), edge_indices(state));
+        let solved = F2LCubeState::new();
+        let mut levels = vec![vec![]; depth + 1];
+        levels[0].push(solved);
+        levels[0].extend(GENERATORS.iter().filter(|g| g.is_u_move()).map(|&g| solved.apply(g)));
+        let mut distances: ::std::collections::HashMap<_, _> = levels[0].iter().map(|s| (key(s), 0)).collect();
+        for distance in 0..depth {
+            for state in levels[distance].clone() {
+                if distances[&key(&state)] != distance {
+                    continue;
+                }
+                for &g in GENERATORS.iter() {
+                    let next = state.apply(g);
+                    let next_distance = distance + metric.cost(&g);
+                    if next_distance <= depth && distances.get(&key(&next)).map_or(true, |&d| next_distance < d) {
+                        distances.insert(key(&next), next_distance);
+                        levels[next_distance].push(next);
+                    }
+                }
+            }
+        }
+        NearSolved { distances: distances, depth: depth }
+    }
+}
+
+#[cfg(test)]
+impl LowerBound for NearSolved {
+    fn lower_bound(&self, state: &F2LCubeState) -> usize {
+        *self.distances.get(&(corner_index(state), edge_indices(state))).unwrap_or(&(self.depth + 1))
+    }
+}
+
+#[test]
+fn finds_shortest_algs() {
+    use cases::case_key;
+    let half_turn = NearSolved::new(Metric::HalfTurn, 4);
+    let quarter_turn = NearSolved::new(Metric::QuarterTurn, 4);
+    assert_eq!(half_turn.lower_bound(&F2LCubeState::new().apply(GENERATORS[12])), 1);
+    let cases = ll_cases();
+    let solve = |alg: &str, metric: Metric| {
+        let key = case_key(&Algorithm::from_str(alg).unwrap());
+        let bound: &LowerBound = if metric == Metric::HalfTurn { &half_turn } else { &quarter_turn };
+        let optimum = cases.iter().find(|c| c.key == key).unwrap().solve_with(metric, bound);
+        assert_eq!(case_key(&Algorithm::from_str(optimum.example.as_str()).unwrap()), key);
+        (optimum.length, optimum.solutions)
+    };
+    assert_eq!(solve("R U R' U R U2 R'", Metric::HalfTurn), (7, 1));
+    assert_eq!(solve("R U R' U R U2 R'", Metric::QuarterTurn), (8, 1));
+    assert_eq!(solve("F R U R' U' F'", Metric::HalfTurn), (6, 1));
+
+    // every case with an alg up to 7 moves long, which the enumeration finds the shortest of first
+    use algorithm_iterator::AlgorithmIterator;
+    let mut shortest = BTreeMap::new();
+    for alg in AlgorithmIterator::new().with_max_length(7).unwrap() {
+        shortest.entry(case_key(&alg)).or_insert(alg);
+    }
+    assert_eq!(shortest.len(), 14);
+    for alg in shortest.values() {
+        assert_eq!(solve(format!("{}", alg).as_str(), Metric::HalfTurn).0, alg.length() as usize);
+    }
+}
+
+#[test]
+fn counts_mirrors_and_inverses() {
+    let optimum = |length| Optimum { length: length, solutions: 1, example: String::new() };
+    let row = |cases, length| CaseOptimum { key: String::new(), subset: "OLL", cases: cases, htm: optimum(length), qtm: optimum(length + 1) };
+    let table = OptimalTable { cases: vec![row(2, 7), row(4, 9), row(1, 7)] };
+    assert_eq!(table.histogram(|c| &c.htm), vec![(7, 3), (9, 4)]);
+    assert_eq!(table.histogram(|c| &c.qtm), vec![(8, 3), (10, 4)]);
+}
@@ -0,0 +1,491 @@
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use rustc_serialize::json::{Json, Object};
+use toml;
+use history::PostedTo;
+use journal::Journal;
+use lla_error::LLAError;
+use mastodon::Mastodon;
+use template::DEFAULT_TEMPLATE;
+use tweet::Twitter;
+use webhook::Webhook;
+
+const PUBLISHERS_FNAME: &'static str = "publishers.json";
+const CONFIG_FNAME: &'static str = "config.toml";
+// where to look for the config instead, if it's set
+const CONFIG_ENV_VAR: &'static str = "LLA_CONFIG";
+
+// Somewhere the bot can post to. Posting takes two steps everywhere, one to upload the image
+// (which gives back an identifier for it) and one to post the status that includes it.
+pub trait Publisher {
+    fn name(&self) -> &str;
+    fn upload_media(&self, filename: &str) -> Result<String, Box<Error>>;
+    // Describes uploaded media for screen readers. Doing it twice has to be harmless, as it's done
+    // again on retries.
+    fn set_alt_text(&self, media_id: &str, alt_text: &str) -> Result<(), Box<Error>>;
+    // Gives back the id of the new post.
+    fn post_status(&self, status: &str, media_id: &str) -> Result<String, Box<Error>>;
+}
+
+pub fn read_file(filename: &str) -> Result<Vec<u8>, Box<Error>> {
+    let mut file = File::open(filename)?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+pub fn base_name(filename: &str) -> String {
+    Path::new(filename).file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or(String::from(filename))
+}
+
+// The environment variable that overrides a setting in a backend's section, e.g.
+// LLA_MASTODON_ACCESS_TOKEN for mastodon's "access_token".
+pub fn env_var_name(backend: &str, key: &str) -> String {
+    format!("LLA_{}_{}", backend, key).to_uppercase()
+}
+
+pub fn required_string(section: &Object, backend: &str, key: &str) -> Result<String, LLAError> {
+    section.get(key)
+        .and_then(|v| v.as_string())
+        .map(String::from)
+        .ok_or(LLAError::InvalidOption(format!("'{}' needs a '{}' string, in its section of the config or as {}",
+                                               backend, key, env_var_name(backend, key))))
+}
+
+pub fn optional_string<'a>(section: &'a Object, key: &str) -> Result<Option<&'a str>, LLAError> {
+    match section.get(key) {
+        Some(v) => v.as_string()
+            .map(Some)
+            .ok_or(LLAError::InvalidOption(format!("'{}' should be a string", key))),
+        None => Ok(None),
+    }
+}
+
+// Turns a post the server turned down with `status` because of its media (going by `says` turning
+// up in what it sent back) into a MediaGone, so that the image gets uploaded again. Anything else
+// comes back as it was.
+pub fn media_gone_if(err: Box<Error>, status: u32, says: &str) -> Box<Error> {
+    let message = format!("{}", err);
+    if message.contains(format!(" gave {}: ", status).as_str()) && message.contains(says) {
+        From::from(LLAError::MediaGone(message))
+    } else {
+        err
+    }
+}
+
+fn backend(name: &str, section: &Object) -> Result<Box<Publisher>, Box<Error>> {
+    match name {
+        "twitter"  => Ok(Box::new(Twitter::from_config(section)?)),
+        "mastodon" => Ok(Box::new(Mastodon::from_config(section)?)),
+        "webhook"  => Ok(Box::new(Webhook::from_config(section)?)),
+        &_ => Err(From::from(LLAError::InvalidOption(format!("No posting backend '{}'", name)))),
+    }
+}
+
+// The backend's section with anything set in the environment laid over the top, so that secrets
+// don't have to be kept in the config file.
+fn with_env_overrides(name: &str, section: &Object, vars: &[(String, String)]) -> Object {
+    let prefix = env_var_name(name, "");
+    let mut section = section.clone();
+    for &(ref var, ref value) in vars {
+        if var.starts_with(prefix.as_str()) && var.len() > prefix.len() {
+            section.insert(var[prefix.len()..].to_lowercase(), Json::String(value.clone()));
+        }
+    }
+    section
+}
+
+fn toml_to_json(value: toml::Value) -> Json {
+    match value {
+        toml::Value::String(s) | toml::Value::Datetime(s) => Json::String(s),
+        toml::Value::Integer(n) => Json::I64(n),
+        toml::Value::Float(f) => Json::F64(f),
+        toml::Value::Boolean(b) => Json::Boolean(b),
+        toml::Value::Array(values) => Json::Array(values.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Json::Object(table.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect()),
+    }
+}
+
+// Where to post to, and what.
+pub struct Config {
+    // each backend's name and its section of the config
+    backends: Vec<(String, Object)>,
+    pub template: String,
+    // a list of case names, in the format `CaseNames` reads
+    pub case_names: Option<String>,
+}
+
+impl Default for Config {
+    // Without a config file the bot just tweets the alg, as it always has.
+    fn default() -> Self {
+        Config {
+            backends: vec![(String::from("twitter"), Object::new())],
+            template: String::from(DEFAULT_TEMPLATE),
+            case_names: None,
+        }
+    }
+}
+
+impl Config {
+    // Reads the config from JSON like
+    //
+    //   {
+    //     "backends": ["twitter", "mastodon", "webhook"],
+    //     "twitter": { "creds": "creds" },
+    //     "mastodon": { "instance": "https://mastodon.social", "access_token": "..." },
+    //     "webhook": { "url": "https://example.com/hook" },
+    //     "template": "{case_name} ({subset}): {alg}",
+    //     "case_names": "case_names.txt"
+    //   }
+    //
+    // Each backend gets its own section, which only has to be there if it has required fields.
+    // Anything else left out keeps its default.
+    pub fn from_json(s: &str) -> Result<Self, Box<Error>> {
+        let json = Json::from_str(s)?;
+        let obj = json.as_object().ok_or(LLAError::InvalidOption(String::from("Publisher config should be an object")))?;
+        Self::from_object(obj)
+    }
+
+    // The same thing in TOML, with each backend's section as a table:
+    //
+    //   backends = ["twitter", "mastodon"]
+    //   template = "{case_name} ({subset}): {alg}"
+    //
+    //   [twitter]
+    //   consumer_key = "..."
+    //
+    //   [mastodon]
+    //   instance = "https://mastodon.social"
+    pub fn from_toml(s: &str) -> Result<Self, Box<Error>> {
+        let mut parser = toml::Parser::new(s);
+        match parser.parse() {
+            Some(table) => match toml_to_json(toml::Value::Table(table)) {
+                Json::Object(obj) => Self::from_object(&obj),
+                _ => unreachable!(),
+            },
+            None => {
+                let errors: Vec<String> = parser.errors.iter().map(|e| {
+                    let (line, col) = parser.to_linecol(e.lo);
+                    format!("line {}, column {}: {}", line + 1, col + 1, e.desc)
+                }).collect();
+                Err(From::from(LLAError::InvalidOption(format!("Bad config: {}", errors.join("; ")))))
+            },
+        }
+    }
+
+    fn from_object(obj: &Object) -> Result<Self, Box<Error>> {
+        let mut config = Self::default();
+
+        if let Some(names) = obj.get("backends") {
+            let names = names.as_array().ok_or(LLAError::InvalidOption(String::from("'backends' should be a list")))?;
+            config.backends = vec![];
+            for name in names {
+                let name = name.as_string().ok_or(LLAError::InvalidOption(String::from("'backends' should be a list of names")))?;
+                let section = match obj.get(name) {
+                    Some(section) => section.as_object().ok_or(LLAError::InvalidOption(format!("'{}' should be an object", name)))?.clone(),
+                    None => Object::new(),
+                };
+                config.backends.push((String::from(name), section));
+            }
+        }
+        if let Some(template) = optional_string(obj, "template")? {
+            config.template = String::from(template);
+        }
+        config.case_names = optional_string(obj, "case_names")?.map(String::from);
+        Ok(config)
+    }
+
+    // Reads the file named by LLA_CONFIG if it's set, otherwise `config.toml` or `publishers.json`
+    // in the working directory, whichever is there.
+    pub fn load() -> Result<Self, Box<Error>> {
+        let filename = match env::var(CONFIG_ENV_VAR) {
+            Ok(f) => f,
+            Err(_) if Path::new(CONFIG_FNAME).exists() => String::from(CONFIG_FNAME),
+            Err(_) if Path::new(PUBLISHERS_FNAME).exists() => String::from(PUBLISHERS_FNAME),
+            Err(_) => return Ok(Self::default()),
+        };
+        let contents = String::from_utf8(read_file(filename.as_str())?)?;
+        if filename.ends_with(".json") {
+            Self::from_json(contents.as_str())
+        } else {
+            Self::from_toml(contents.as_str())
+        }
+    }
+
+    pub fn publishers(&self) -> Result<Vec<Box<Publisher>>, Box<Error>> {
+        self.publishers_with_env(&env::vars().collect::<Vec<_>>())
+    }
+
+    fn publishers_with_env(&self, vars: &[(String, String)]) -> Result<Vec<Box<Publisher>>, Box<Error>> {
+        self.backends.iter()
+            .map(|&(ref name, ref section)| backend(name.as_str(), &with_env_overrides(name.as_str(), section, vars)))
+            .collect()
+    }
+}
+
+// Stands in for the real backends with `tweet --dry-run`, leaving what would have been posted in
+// a directory: the image under its own name, the text in `status.txt` and the image's
+// description in `alt_text.txt`.
+pub struct DryRun {
+    dir: String,
+}
+
+impl DryRun {
+    pub fn new(dir: &str) -> Self {
+        DryRun { dir: String::from(dir) }
+    }
+}
+
+impl Publisher for DryRun {
+    fn name(&self) -> &str {
+        "dry run"
+    }
+
+    fn upload_media(&self, filename: &str) -> Result<String, Box<Error>> {
+        fs::create_dir_all(&self.dir)?;
+        let dest = Path::new(&self.dir).join(base_name(filename));
+        fs::copy(filename, &dest)?;
+        Ok(dest.to_string_lossy().into_owned())
+    }
+
+    fn set_alt_text(&self, _media_id: &str, alt_text: &str) -> Result<(), Box<Error>> {
+        File::create(Path::new(&self.dir).join("alt_text.txt"))?.write_all(alt_text.as_bytes())?;
+        Ok(())
+    }
+
+    fn post_status(&self, status: &str, _media_id: &str) -> Result<String, Box<Error>> {
+        let dest = Path::new(&self.dir).join("status.txt");
+        File::create(&dest)?.write_all(status.as_bytes())?;
+        Ok(dest.to_string_lossy().into_owned())
+    }
+}
+
+// Uploads the image and records that it's there.
+fn upload(p: &Publisher, journal: &mut Journal, filename: &str) -> Result<String, Box<Error>> {
+    let id = p.upload_media(filename)?;
+    journal.record_media(p.name(), id.as_str())?;
+    Ok(id)
+}
+
+fn post_with_media(p: &Publisher, status: &str, media_id: &str, alt_text: &str) -> Result<String, Box<Error>> {
+    // better to post without a description than not at all
+    if let Err(why) = p.set_alt_text(media_id, alt_text) {
+        eprintln!("Couldn't set the alt text on {}: {}", p.name(), why);
+    }
+    p.post_status(status, media_id)
+}
+
+// Posts to one backend, skipping whatever the journal says was already done for it.
+fn publish_to(p: &Publisher, journal: &mut Journal, status: &str, filename: &str, alt_text: &str) -> Result<String, Box<Error>> {
+    let progress = journal.progress(p.name()).cloned();
+    if let Some(post_id) = progress.as_ref().and_then(|pr| pr.post_id.clone()) {
+        println!("Already posted to {} as {}", p.name(), post_id);
+        return Ok(post_id);
+    }
+    let post_id = match progress.and_then(|pr| pr.media_id) {
+        Some(id) => match post_with_media(p, status, id.as_str(), alt_text) {
+            Ok(post_id) => post_id,
+            // platforms throw away uploads that don't get posted, so one from an earlier run may
+            // well be gone. Without uploading it again it would never get posted.
+            Err(why) => match why.downcast_ref::<LLAError>() {
+                Some(&LLAError::MediaGone(_)) => {
+                    eprintln!("{} no longer has the image uploaded before ({}), uploading it again", p.name(), why);
+                    journal.clear_media(p.name())?;
+                    let id = upload(p, journal, filename)?;
+                    post_with_media(p, status, id.as_str(), alt_text)?
+                },
+                // the post may have gone through anyway, so the next run tries again with the
+                // same image rather than risk posting it twice
+                _ => return Err(why),
+            },
+        },
+        None => {
+            let id = upload(p, journal, filename)?;
+            post_with_media(p, status, id.as_str(), alt_text)?
+        },
+    };
+    journal.record_post(p.name(), post_id.as_str())?;
+    println!("Posted to {} as {}", p.name(), post_id);
+    Ok(post_id)
+}
+
+// Posts the journal's pending alg to every backend, carrying on past any that fail so that one
+// platform being down doesn't stop the rest, and gives back where it went. It's only an error if
+// it didn't get posted anywhere, in which case it stays pending for the next run to try again.
+// Otherwise it's up to the caller to `finish` it in the journal.
+pub fn publish_everywhere(publishers: &[Box<Publisher>], journal: &mut Journal, filename: &str, alt_text: &str) -> Result<Vec<PostedTo>, Box<Error>> {
+    let status = journal.pending().ok_or("Nothing to post")?.status.clone();
+    let mut posts = vec![];
+    let mut failures = vec![];
+    for p in publishers {
+        match publish_to(p.as_ref(), journal, status.as_str(), filename, alt_text) {
+            Ok(post_id) => posts.push(PostedTo { backend: String::from(p.name()), post_id: post_id }),
+            Err(why) => {
+                eprintln!("Couldn't post to {}: {}", p.name(), why);
+                failures.push(format!("{}: {}", p.name(), why));
+            }
+        }
+    }
+    if !publishers.is_empty() && posts.is_empty() {
+        return Err(From::from(format!("Couldn't post anywhere ({})", failures.join("; "))));
+    }
+    Ok(posts)
+}
+
+#[test]
+fn dry_run_writes_status_and_image() {
+    let dir = ::std::env::temp_dir().join(format!("lla-dry-run-{}", ::std::process::id()));
+    let image = ::std::env::temp_dir().join(format!("lla-dry-run-{}.png", ::std::process::id()));
+    File::create(&image).unwrap().write_all(b"PNG").unwrap();
+
+    let publishers: Vec<Box<Publisher>> = vec![Box::new(DryRun::new(dir.to_str().unwrap()))];
+    let mut journal = Journal::in_memory();
+    journal.start("R U R' U R U2 R'", "R U R' U R U2 R'").unwrap();
+    publish_everywhere(&publishers, &mut journal, image.to_str().unwrap(), "A case").unwrap();
+    assert_eq!(read_file(dir.join("status.txt").to_str().unwrap()).unwrap(), b"R U R' U R U2 R'".to_vec());
+    assert_eq!(read_file(dir.join("alt_text.txt").to_str().unwrap()).unwrap(), b"A case".to_vec());
+    assert_eq!(read_file(dir.join(base_name(image.to_str().unwrap())).to_str().unwrap()).unwrap(), b"PNG".to_vec());
+
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_file(&image).unwrap();
+}
+
+// Uploads fine but only posts if it's `working`, counting the uploads.
+#[cfg(test)]
+struct Flaky {
+    working: bool,
+    uploads: ::std::rc::Rc<::std::cell::Cell<usize>>,
+}
+
+#[cfg(test)]
+impl Publisher for Flaky {
+    fn name(&self) -> &str {
+        "flaky"
+    }
+
+    fn upload_media(&self, _filename: &str) -> Result<String, Box<Error>> {
+        self.uploads.set(self.uploads.get() + 1);
+        Ok(String::from("media"))
+    }
+
+    fn set_alt_text(&self, _media_id: &str, _alt_text: &str) -> Result<(), Box<Error>> {
+        Ok(())
+    }
+
+    fn post_status(&self, _status: &str, _media_id: &str) -> Result<String, Box<Error>> {
+        if self.working { Ok(String::from("post")) } else { Err(From::from("down")) }
+    }
+}
+
+#[test]
+fn retries_pick_up_where_they_left_off() {
+    let uploads = ::std::rc::Rc::new(::std::cell::Cell::new(0));
+    let mut journal = Journal::in_memory();
+    journal.start("R U R' U R U2 R'", "R U R' U R U2 R'").unwrap();
+
+    let down: Vec<Box<Publisher>> = vec![Box::new(Flaky { working: false, uploads: uploads.clone() })];
+    assert!(publish_everywhere(&down, &mut journal, "unused.png", "").is_err());
+    assert_eq!(journal.progress("flaky").unwrap().media_id, Some(String::from("media")));
+    assert!(journal.pending().is_some());
+
+    // the image that already went up gets used rather than uploaded again
+    let up: Vec<Box<Publisher>> = vec![Box::new(Flaky { working: true, uploads: uploads.clone() })];
+    let posts = publish_everywhere(&up, &mut journal, "unused.png", "").unwrap();
+    assert_eq!(uploads.get(), 1);
+    assert_eq!(posts, vec![PostedTo { backend: String::from("flaky"), post_id: String::from("post") }]);
+}
+
+// Like a platform that throws away uploads that aren't posted: posting fails with any image but
+// the latest one uploaded.
+#[cfg(test)]
+struct Expiring {
+    uploads: ::std::rc::Rc<::std::cell::Cell<usize>>,
+}
+
+#[cfg(test)]
+impl Publisher for Expiring {
+    fn name(&self) -> &str {
+        "expiring"
+    }
+
+    fn upload_media(&self, _filename: &str) -> Result<String, Box<Error>> {
+        self.uploads.set(self.uploads.get() + 1);
+        Ok(format!("media-{}", self.uploads.get()))
+    }
+
+    fn set_alt_text(&self, _media_id: &str, _alt_text: &str) -> Result<(), Box<Error>> {
+        Ok(())
+    }
+
+    fn post_status(&self, _status: &str, media_id: &str) -> Result<String, Box<Error>> {
+        if media_id == format!("media-{}", self.uploads.get()) {
+            Ok(String::from("post"))
+        } else {
+            Err(From::from(LLAError::MediaGone(format!("no media {}", media_id))))
+        }
+    }
+}
+
+#[test]
+fn uploads_again_if_the_old_image_is_gone() {
+    let uploads = ::std::rc::Rc::new(::std::cell::Cell::new(0));
+    let mut journal = Journal::in_memory();
+    journal.start("R U R' U R U2 R'", "R U R' U R U2 R'").unwrap();
+    // as if an earlier run had uploaded it and then failed to post it, and the platform has since
+    // dropped it
+    journal.record_media("expiring", "gone").unwrap();
+
+    let publishers: Vec<Box<Publisher>> = vec![Box::new(Expiring { uploads: uploads.clone() })];
+    let posts = publish_everywhere(&publishers, &mut journal, "unused.png", "").unwrap();
+    assert_eq!(uploads.get(), 1);
+    assert_eq!(journal.progress("expiring").unwrap().media_id, Some(String::from("media-1")));
+    assert_eq!(posts, vec![PostedTo { backend: String::from("expiring"), post_id: String::from("post") }]);
+}
+
+#[test]
+fn reads_backends_from_config() {
+    let config = Config::from_json(r#"{
+        "backends": ["mastodon", "webhook"],
+        "mastodon": { "instance": "https://mastodon.social", "access_token": "abc" },
+        "webhook": { "url": "http://localhost/hook" },
+        "template": "{index}: {alg}"
+    }"#).unwrap();
+    let publishers = config.publishers().unwrap();
+    let names: Vec<&str> = publishers.iter().map(|p| p.name()).collect();
+    assert_eq!(names, vec!["mastodon", "webhook"]);
+    assert_eq!(config.template, "{index}: {alg}");
+    assert_eq!(config.case_names, None);
+
+    assert_eq!(Config::from_json("{}").unwrap().template, DEFAULT_TEMPLATE);
+    assert!(Config::from_json(r#"{"backends": ["myspace"]}"#).unwrap().publishers().is_err());
+    assert!(Config::from_json(r#"{"backends": ["mastodon"], "mastodon": {"instance": "https://mastodon.social"}}"#).unwrap().publishers().is_err());
+}
+
+#[test]
+fn reads_toml_config_and_environment() {
+    let config = Config::from_toml(r#"
+        backends = ["mastodon"]
+        template = "{index}: {alg}"
+
+        [mastodon]
+        instance = "https://mastodon.social"
+    "#).unwrap();
+    assert_eq!(config.template, "{index}: {alg}");
+    let err = config.publishers_with_env(&[]).err().unwrap();
+    assert!(format!("{}", err).contains("'access_token'"));
+    assert!(format!("{}", err).contains("LLA_MASTODON_ACCESS_TOKEN"));
+
+    let vars = vec![(String::from("LLA_MASTODON_ACCESS_TOKEN"), String::from("abc")),
+                    (String::from("LLA_WEBHOOK_URL"), String::from("http://localhost/hook"))];
+    let publishers = config.publishers_with_env(&vars).unwrap();
+    assert_eq!(publishers.iter().map(|p| p.name()).collect::<Vec<_>>(), vec!["mastodon"]);
+
+    let section = with_env_overrides("mastodon", &config.backends[0].1, &vars);
+    assert_eq!(section.get("access_token"), Some(&Json::String(String::from("abc"))));
+    assert_eq!(section.get("url"), None);
+
+    assert!(Config::from_toml("backends = [").is_err());
+}
@@ -0,0 +1,183 @@
+use std::error::Error;
+use std::io::Write;
+use std::str::FromStr;
+use rustc_serialize::json;
+use algorithm::Algorithm;
+use cases::{CaseNames, case_key};
+use lla_error::LLAError;
+
+// Everything worth knowing about an alg, worked out once, for printing as JSON or CSV so that
+// whatever reads it doesn't have to make sense of the moves itself.
+#[derive(RustcEncodable, Clone, Debug, PartialEq)]
+pub struct AlgRecord {
+    pub alg: String,
+    // half turn metric, a half turn being one move
+    pub htm: usize,
+    // quarter turn metric, a half turn being two
+    pub qtm: usize,
+    // slice turn metric, which is always the same as htm, there being no slice moves
+    pub stm: usize,
+    pub canonical: String,
+    pub inverse: String,
+    pub case_key: String,
+    pub case_name: Option<String>,
+    // where each sticker of a solved cube ends up after the alg, numbered as in `CubeState`
+    pub state: Vec<u8>,
+}
+
+impl AlgRecord {
+    pub fn new(alg: &Algorithm, names: &CaseNames) -> Self {
+        AlgRecord {
+            alg: format!("{}", alg),
+            htm: alg.length() as usize,
+            qtm: alg.quarter_turn_length(),
+            stm: alg.length() as usize,
+            canonical: format!("{}", alg.canonical_rotation()),
+            inverse: format!("{}", alg.inverse()),
+            case_key: case_key(alg),
+            case_name: names.name(alg).map(String::from),
+            state: alg.cube().state.to_vec(),
+        }
+    }
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.alg.clone(),
+            self.htm.to_string(),
+            self.qtm.to_string(),
+            self.stm.to_string(),
+            self.canonical.clone(),
+            self.inverse.clone(),
+            self.case_key.clone(),
+            self.case_name.clone().unwrap_or(String::new()),
+            self.state.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(" "),
+        ]
+    }
+}
+
+const CSV_HEADER: &'static str = "alg,htm,qtm,stm,canonical,inverse,case_key,case_name,state";
+
+// Quoted only if it has to be, which for algs it never does.
+fn csv_field(s: &str) -> String {
+    if s.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", s.replace("\"", "\"\""))
+    } else {
+        String::from(s)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RecordFormat {
+    // one JSON array of them all
+    Json,
+    // one JSON object a line, which can be read as it comes
+    Jsonl,
+    // with a header line
+    Csv,
+}
+
+impl FromStr for RecordFormat {
+    type Err = LLAError;
+
+    fn from_str(s: &str) -> Result<Self, LLAError> {
+        match s {
+            "json" => Ok(RecordFormat::Json),
+            "jsonl" => Ok(RecordFormat::Jsonl),
+            "csv" => Ok(RecordFormat::Csv),
+            _ => Err(LLAError::InvalidOption(format!("'{}' isn't an output format, try json, jsonl or csv", s))),
+        }
+    }
+}
+
+// Writes records out one at a time as they're made, `finish` closing off whatever needs it.
+pub struct RecordWriter<W: Write> {
+    out: W,
+    format: RecordFormat,
+    written: usize,
+}
+
+impl<W: Write> RecordWriter<W> {
+    pub fn new(out: W, format: RecordFormat) -> Self {
+        RecordWriter { out: out, format: format, written: 0 }
+    }
+
+    pub fn write(&mut self, record: &AlgRecord) -> Result<(), Box<Error>> {
+        let first = self.written == 0;
+        match self.format {
+            RecordFormat::Json => {
+                let separator = if first { "[\n" } else { ",\n" };
+                write!(self.out, "{}  {}", separator, json::encode(record)?)?;
+            },
+            RecordFormat::Jsonl => writeln!(self.out, "{}", json::encode(record)?)?,
+            RecordFormat::Csv => {
+                if first {
+                    writeln!(self.out, "{}", CSV_HEADER)?;
+                }
+                let fields: Vec<String> = record.csv_fields().iter().map(|f| csv_field(f)).collect();
+                writeln!(self.out, "{}", fields.join(","))?;
+            },
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, Box<Error>> {
+        match self.format {
+            RecordFormat::Json if self.written == 0 => writeln!(self.out, "[]")?,
+            RecordFormat::Json => writeln!(self.out, "\n]")?,
+            RecordFormat::Csv if self.written == 0 => writeln!(self.out, "{}", CSV_HEADER)?,
+            _ => (),
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[test]
+fn describes_algs() {
+    let names = CaseNames::parse("Sune,R U R' U R U2 R'").unwrap();
+    let record = AlgRecord::new(&Algorithm::from_str("F U F' U F U2 F'").unwrap(), &names);
+    assert_eq!(record.alg, "F U F' U F U2 F'");
+    assert_eq!((record.htm, record.qtm, record.stm), (7, 8, 7));
+    assert_eq!(record.canonical, "R U R' U R U2 R'");
+    assert_eq!(record.inverse, "F U2 F' U' F U' F'");
+    assert_eq!(record.case_key, case_key(&Algorithm::from_str("R U R' U R U2 R'").unwrap()));
+    assert_eq!(record.case_name, Some(String::from("Sune")));
+    assert_eq!(record.state.len(), 54);
+    // the F2L isn't touched
+    assert_eq!(record.state[40], 40);
+    assert_eq!(AlgRecord::new(&Algorithm::from_str("R U2 R' U' R U' R'").unwrap(), &names).case_name, None);
+}
+
+#[test]
+fn writes_each_format() {
+    let mut sune = AlgRecord::new(&Algorithm::from_str("R U R' U R U2 R'").unwrap(), &CaseNames::empty());
+    sune.case_name = Some(String::from("Sune, \"the\" first"));
+    let write = |format: RecordFormat, records: &[&AlgRecord]| {
+        let mut writer = RecordWriter::new(vec![], format);
+        for record in records {
+            writer.write(record).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    };
+
+    let jsonl = write(RecordFormat::Jsonl, &[&sune, &sune]);
+    assert_eq!(jsonl.lines().count(), 2);
+    let parsed = json::Json::from_str(jsonl.lines().next().unwrap()).unwrap();
+    assert_eq!(parsed.find("case_name").and_then(|n| n.as_string()), Some("Sune, \"the\" first"));
+    assert_eq!(parsed.find("qtm").and_then(|n| n.as_u64()), Some(8));
+
+    let parsed = json::Json::from_str(write(RecordFormat::Json, &[&sune, &sune]).as_str()).unwrap();
+    assert_eq!(parsed.as_array().map(|a| a.len()), Some(2));
+    assert_eq!(write(RecordFormat::Json, &[]), "[]\n");
+
+    let csv = write(RecordFormat::Csv, &[&sune]);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], CSV_HEADER);
+    assert!(lines[1].starts_with("R U R' U R U2 R',7,8,7,R U R' U R U2 R',R U2 R' U' R U' R',"));
+    assert!(lines[1].contains(",\"Sune, \"\"the\"\" first\","));
+    assert_eq!(lines[1].rsplit(',').next().unwrap().split(' ').count(), 54);
+    assert_eq!(write(RecordFormat::Csv, &[]), format!("{}\n", CSV_HEADER));
+
+    assert!(RecordFormat::from_str("xml").is_err());
+}
@@ -0,0 +1,171 @@
+use std::str::FromStr;
+use lla_error::LLAError;
+
+// When to post, written like a cron schedule: five fields, for the minute (0-59), hour (0-23),
+// day of the month (1-31), month (1-12) and day of the week (0-6, Sunday being 0 or 7). Each
+// field is `*`, a number or a range like `1-5`, any of which can take a step like `*/15`, or a
+// comma separated list of those. As in cron, if both the day of the month and the day of the week
+// are given, a day matching either will do. Times are all UTC.
+pub struct Schedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+fn parse_number(s: &str, field: &str) -> Result<usize, LLAError> {
+    s.parse().map_err(|_| LLAError::InvalidOption(format!("'{}' isn't a number in schedule field '{}'", s, field)))
+}
+
+// Which values from 0 to `max` the field allows, none below `min` being allowed.
+fn parse_field(field: &str, min: usize, max: usize) -> Result<Vec<bool>, LLAError> {
+    let mut allowed = vec![false; max + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(idx) => (&part[..idx], parse_number(&part[idx + 1..], field)?),
+            None => (part, 1),
+        };
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some(idx) = range.find('-') {
+            (parse_number(&range[..idx], field)?, parse_number(&range[idx + 1..], field)?)
+        } else {
+            // `5/15` means every 15 starting from 5
+            let n = parse_number(range, field)?;
+            (n, if step > 1 { max } else { n })
+        };
+        if lo < min || hi > max || lo > hi || step == 0 {
+            return Err(LLAError::InvalidOption(format!("'{}' is out of range in schedule field '{}', which goes from {} to {}",
+                                                       part, field, min, max)));
+        }
+        let mut v = lo;
+        while v <= hi {
+            allowed[v] = true;
+            v += step;
+        }
+    }
+    Ok(allowed)
+}
+
+impl FromStr for Schedule {
+    type Err = LLAError;
+
+    fn from_str(s: &str) -> Result<Self, LLAError> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(LLAError::InvalidOption(format!("A schedule needs five fields (minute hour day month weekday), not '{}'", s)));
+        }
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        weekdays[0] = weekdays[0] || weekdays[7];
+        Ok(Schedule {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays: weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+}
+
+impl Schedule {
+    fn day_matches(&self, day: usize, weekday: usize) -> bool {
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => self.days[day],
+            (true, false) => self.weekdays[weekday],
+            (false, false) => self.days[day] || self.weekdays[weekday],
+        }
+    }
+
+    // The first time the schedule fires after `t`, in seconds since the epoch, or None if it
+    // never does (e.g. on the 30th of February).
+    pub fn next_after(&self, t: u64) -> Option<u64> {
+        let first_day = (t / 86400) as i64;
+        // a leap day can be as much as eight years off
+        for day in first_day..first_day + 366 * 8 {
+            let (_, month, day_of_month) = civil_from_days(day);
+            // the epoch was a Thursday
+            let weekday = ((day + 4) % 7) as usize;
+            if !self.months[month as usize] || !self.day_matches(day_of_month as usize, weekday) {
+                continue;
+            }
+            for hour in (0..24).filter(|&h| self.hours[h]) {
+                for minute in (0..60).filter(|&m| self.minutes[m]) {
+                    let time = day as u64 * 86400 + hour as u64 * 3600 + minute as u64 * 60;
+                    if time > t {
+                        return Some(time);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+// Days since the epoch of a date, and the other way around, counting years from March so that
+// leap days come at the end. See http://howardhinnant.github.io/date_algorithms.html
+pub fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((m as i64 + 9) % 12) + 2) / 5 + d as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = (if days >= 0 { days } else { days - 146096 }) / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let d = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let m = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let y = year_of_era + era * 400;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+// e.g. "2017-07-14T02:40:00Z"
+pub fn format_time(t: u64) -> String {
+    let (y, m, d) = civil_from_days((t / 86400) as i64);
+    let secs = t % 86400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, m, d, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[test]
+fn finds_next_time() {
+    // a Friday
+    let now = 1_500_000_000;
+    assert_eq!(format_time(now), "2017-07-14T02:40:00Z");
+    let next = |s: &str| Schedule::from_str(s).unwrap().next_after(now).map(format_time);
+    assert_eq!(next("0 12 * * *"), Some(String::from("2017-07-14T12:00:00Z")));
+    assert_eq!(next("*/15 * * * *"), Some(String::from("2017-07-14T02:45:00Z")));
+    assert_eq!(next("40 2 * * *"), Some(String::from("2017-07-15T02:40:00Z")));
+    assert_eq!(next("0 9 * * 1-5"), Some(String::from("2017-07-14T09:00:00Z")));
+    assert_eq!(next("30 9,17 * * 7"), Some(String::from("2017-07-16T09:30:00Z")));
+    // either the 1st or a Monday
+    assert_eq!(next("0 0 1 * 1"), Some(String::from("2017-07-17T00:00:00Z")));
+    assert_eq!(next("0 0 29 2 *"), Some(String::from("2020-02-29T00:00:00Z")));
+    assert_eq!(next("0 0 30 2 *"), None);
+
+    assert!(Schedule::from_str("0 12 * *").is_err());
+    assert!(Schedule::from_str("60 * * * *").is_err());
+    assert!(Schedule::from_str("noon * * * *").is_err());
+    assert!(Schedule::from_str("*/0 * * * *").is_err());
+}
+
+#[test]
+fn converts_dates() {
+    assert_eq!(days_from_civil(1970, 1, 1), 0);
+    assert_eq!(days_from_civil(2000, 3, 1), 11017);
+    for &days in [-1, 0, 11016, 11017, 17361, 20000].iter() {
+        let (y, m, d) = civil_from_days(days);
+        assert_eq!(days_from_civil(y, m, d), days);
+    }
+}
@@ -0,0 +1,166 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::str::FromStr;
+use rand::Rng;
+use algorithm::Algorithm;
+use cases::subset_of_case;
+use cubestate::CubeState;
+use generator::{Face, Generator, Modifier};
+use optimal::ll_states;
+
+// Scrambles for practising the last layer: a state picked at random from all of them, and moves
+// that leave a solved cube in it. The moves are a solution for the state turned round, made up
+// of the shortest chain of algs from a list (and U turns) that gets there, which is the shortest
+// alg there is for any state with an alg in the list.
+
+// The stickers the last layer can move, which are enough to tell its states apart.
+fn ll_stickers(c: &CubeState) -> [u8; 21] {
+    let mut result = [0; 21];
+    for (i, s) in (0..18).chain(51..54).enumerate() {
+        result[i] = c.state[s];
+    }
+    result
+}
+
+// Joins the moves up, cancelling or merging any that turn the same face one after the other, e.g.
+// `R U` then `U R'` comes to `R U2 R'`.
+fn join(moves: &[Generator]) -> Algorithm {
+    let mut joined: Vec<(Face, usize)> = vec![];
+    for m in moves {
+        let (face, modifier) = m.components();
+        let turns = match modifier {
+            Modifier::Normal => 1,
+            Modifier::Twice => 2,
+            Modifier::Prime => 3,
+        };
+        match joined.pop() {
+            Some((last, last_turns)) if last == face => {
+                if (last_turns + turns) % 4 != 0 {
+                    joined.push((face, (last_turns + turns) % 4));
+                }
+            },
+            Some(last) => joined.extend(vec![last, (face, turns)]),
+            None => joined.push((face, turns)),
+        }
+    }
+    let text: Vec<String> = joined.iter().map(|&(face, turns)| format!("{}{}", face, ["", "", "2", "'"][turns])).collect();
+    Algorithm::from_str(text.join(" ").as_str()).unwrap()
+}
+
+pub struct Scrambler {
+    states: Vec<CubeState>,
+    index: HashMap<[u8; 21], usize>,
+    // for each state, the one before it on the way there from solved and the alg that got it there
+    came_from: Vec<Option<(usize, usize)>>,
+    algs: Vec<Algorithm>,
+    // the states to pick from at random, all but those only needing an AUF, and those in each subset
+    unsolved: Vec<usize>,
+    by_subset: HashMap<&'static str, Vec<usize>>,
+}
+
+impl Scrambler {
+    // Works out how to get to every state using `algs`, so that they take as few moves as they
+    // can. The more algs, the shorter the scrambles, but the longer it takes.
+    pub fn new(mut algs: Vec<Algorithm>) -> Self {
+        for u in ["U", "U2", "U'"].iter() {
+            algs.push(Algorithm::from_str(u).unwrap());
+        }
+        let effects: Vec<CubeState> = algs.iter().map(|a| a.cube()).collect();
+        let states = ll_states();
+        let index: HashMap<[u8; 21], usize> = states.iter().enumerate().map(|(i, s)| (ll_stickers(s), i)).collect();
+        let solved = index[&ll_stickers(&CubeState::solved())];
+
+        let mut distance = vec![usize::max_value(); states.len()];
+        let mut came_from = vec![None; states.len()];
+        let mut queue = BinaryHeap::new();
+        distance[solved] = 0;
+        queue.push(Reverse((0, solved)));
+        while let Some(Reverse((d, i))) = queue.pop() {
+            if d > distance[i] {
+                continue;
+            }
+            for (a, effect) in effects.iter().enumerate() {
+                let next = index[&ll_stickers(&states[i].apply(effect))];
+                let next_distance = d + algs[a].length() as usize;
+                if next_distance < distance[next] {
+                    distance[next] = next_distance;
+                    came_from[next] = Some((i, a));
+                    queue.push(Reverse((next_distance, next)));
+                }
+            }
+        }
+
+        let mut unsolved = vec![];
+        let mut by_subset = HashMap::new();
+        for (i, state) in states.iter().enumerate() {
+            let subset = subset_of_case(state);
+            if subset != "AUF" {
+                unsolved.push(i);
+                by_subset.entry(subset).or_insert(vec![]).push(i);
+            }
+        }
+        Scrambler { states: states, index: index, came_from: came_from, algs: algs, unsolved: unsolved, by_subset: by_subset }
+    }
+
+    // Moves leaving a solved cube in the state, if the algs can get there.
+    pub fn scramble(&self, state: &CubeState) -> Option<Algorithm> {
+        let mut i = *self.index.get(&ll_stickers(state))?;
+        let mut algs = vec![];
+        while let Some((before, alg)) = self.came_from[i] {
+            algs.push(alg);
+            i = before;
+        }
+        if i != self.index[&ll_stickers(&CubeState::solved())] {
+            return None;
+        }
+        let moves: Vec<Generator> = algs.iter().rev().flat_map(|&a| self.algs[a].moves.iter().cloned()).collect();
+        Some(join(&moves))
+    }
+
+    // A state picked at random from all of those in the given subset (PLL, OLL, ZBLL or 1LLL, as
+    // `cases::subset` says), or from all of them if there isn't one, but never one that only needs
+    // an AUF. Each state is as likely as any other, so cases come up as often as they would in a
+    // solve.
+    pub fn random_state<R: Rng>(&self, rng: &mut R, subset: Option<&str>) -> Option<CubeState> {
+        let states: &[usize] = match subset {
+            Some(wanted) => self.by_subset.get(wanted).map(|v| &v[..]).unwrap_or(&[]),
+            None => &self.unsolved,
+        };
+        if states.is_empty() {
+            None
+        } else {
+            Some(self.states[states[rng.gen_range(0, states.len())]])
+        }
+    }
+}
+
+#[test]
+fn joins_moves() {
+    let moves = Algorithm::from_str("R U U R' R U2 F F' U'").unwrap().moves;
+    assert_eq!(format!("{}", join(&moves)), "R U'");
+    assert_eq!(format!("{}", join(&Algorithm::from_str("R U' U R'").unwrap().moves)), "");
+}
+
+#[test]
+fn scrambles_every_state() {
+    use algorithm_iterator::AlgorithmIterator;
+    let scrambler = Scrambler::new(AlgorithmIterator::new().with_max_length(7).unwrap().collect());
+    assert!(scrambler.came_from.iter().filter(|c| c.is_none()).count() == 1);
+
+    let sune = Algorithm::from_str("R U R' U R U2 R'").unwrap();
+    let case = sune.inverse().cube();
+    let scramble = scrambler.scramble(&case).unwrap();
+    assert_eq!(scramble.length(), 7);
+    assert!(scramble.cube() == case);
+
+    let mut rng = ::rand::thread_rng();
+    for subset in [None, Some("PLL"), Some("OLL"), Some("ZBLL")].iter() {
+        let state = scrambler.random_state(&mut rng, *subset).unwrap();
+        assert!(subset.map_or(true, |s| subset_of_case(&state) == s));
+        assert!(scrambler.scramble(&state).unwrap().cube() == state);
+    }
+    assert!(scrambler.random_state(&mut rng, Some("F2L")).is_none());
+    // every state but solved and the three U turns, each in one subset
+    assert_eq!(scrambler.unsolved.len(), 62204);
+    assert_eq!(scrambler.by_subset.values().map(|v| v.len()).sum::<usize>(), 62204);
+}
@@ -0,0 +1,138 @@
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use cairo::surface::Surface;
+use cairo::Cairo;
+use cairo::surface::format::Format;
+use cases::AlgEntry;
+use image_generator::{draw_case, set_up_canvas, ImageOptions, FWIDTH, FHEIGHT};
+use image_style::ImageStyle;
+use lla_error::LLAError;
+
+// Study sheets: a grid of cases per page, each with its alg and name underneath. Pages are A4,
+// measured in points.
+const PAGE_WIDTH: f64 = 595.;
+const PAGE_HEIGHT: f64 = 842.;
+const PAGE_MARGIN: f64 = 36.;
+const CELL_PADDING: f64 = 6.;
+const FONT_SIZE: f64 = 9.;
+const LINE_HEIGHT: f64 = 12.;
+// Rough width of a character relative to the font size, used to shrink long algs to fit
+const CHAR_WIDTH: f64 = 0.6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SheetLayout {
+    pub columns: usize,
+    pub rows: usize,
+}
+
+impl Default for SheetLayout {
+    fn default() -> Self {
+        SheetLayout { columns: 3, rows: 5 }
+    }
+}
+
+impl SheetLayout {
+    // A layout with at least one case across and down each page.
+    pub fn new(columns: usize, rows: usize) -> Result<Self, LLAError> {
+        if columns < 1 || rows < 1 {
+            return Err(LLAError::InvalidOption(format!("A sheet needs at least one column and row, not {}x{}", columns, rows)));
+        }
+        Ok(SheetLayout { columns: columns, rows: rows })
+    }
+
+    fn per_page(&self) -> usize {
+        self.columns * self.rows
+    }
+}
+
+fn draw_text(cr: &mut Cairo, x: f64, y: f64, max_width: f64, text: &str) {
+    let fitting_size = max_width / (CHAR_WIDTH * text.len() as f64);
+    cr.set_source_rgba(0., 0., 0., 1.);
+    cr.set_font_size(FONT_SIZE.min(fitting_size));
+    cr.move_to(x, y);
+    cr.show_text(text);
+}
+
+// Draws one page's worth of entries, `first` being the number of the first one on it.
+fn draw_page(cr: &mut Cairo, entries: &[AlgEntry], first: usize, layout: &SheetLayout, options: &ImageOptions) {
+    let cell_width = (PAGE_WIDTH - 2. * PAGE_MARGIN) / layout.columns as f64;
+    let cell_height = (PAGE_HEIGHT - 2. * PAGE_MARGIN) / layout.rows as f64;
+    let text_height = 2. * LINE_HEIGHT;
+    let inner_width = cell_width - 2. * CELL_PADDING;
+    let scale = (inner_width / FWIDTH).min((cell_height - 2. * CELL_PADDING - text_height) / FHEIGHT);
+
+    for (i, entry) in entries.iter().enumerate() {
+        let x = PAGE_MARGIN + (i % layout.columns) as f64 * cell_width + CELL_PADDING;
+        let y = PAGE_MARGIN + (i / layout.columns) as f64 * cell_height + CELL_PADDING;
+
+        cr.save();
+        cr.translate(x + (inner_width - FWIDTH * scale) / 2., y);
+        cr.scale(scale, scale);
+        draw_case(cr, entry.alg.inverse().cube(), options);
+        cr.restore();
+
+        let label = match entry.name {
+            Some(ref name) => format!("{}. {}", first + i + 1, name),
+            None => format!("{}.", first + i + 1),
+        };
+        let text_top = y + FHEIGHT * scale;
+        draw_text(cr, x, text_top + LINE_HEIGHT, inner_width, label.as_str());
+        draw_text(cr, x, text_top + 2. * LINE_HEIGHT, inner_width, format!("{}", entry.alg).as_str());
+    }
+}
+
+// The cairo bindings only make image surfaces, but the cairo library they link to has PDF
+// surfaces too, and they're drawn on just the same once made.
+extern {
+    fn cairo_pdf_surface_create(filename: *const c_char, width_in_points: f64, height_in_points: f64) -> *mut c_void;
+}
+
+fn create_pdf_surface(filename: &str, width: f64, height: f64) -> Surface {
+    let filename = CString::new(filename).unwrap();
+    // cairo copies the filename, and gives back an error surface rather than null if it can't
+    // make one
+    let surface = unsafe { cairo_pdf_surface_create(filename.as_ptr(), width, height) };
+    Surface { opaque: surface as *mut _ }
+}
+
+// Draws the sheet as a PDF with a page for every `layout.per_page()` entries.
+pub fn generate_pdf_sheet(entries: &[AlgEntry], output_filename: &str, layout: &SheetLayout, options: &ImageOptions) {
+    // PDFs are always A4, so any image size in the style doesn't apply
+    let style = ImageStyle { width: None, height: None, ..options.style };
+    let mut surface = create_pdf_surface(output_filename, PAGE_WIDTH, PAGE_HEIGHT);
+    let mut cr = Cairo::create(&mut surface);
+
+    for (page, chunk) in entries.chunks(layout.per_page()).enumerate() {
+        cr.save();
+        set_up_canvas(&mut cr, &style, PAGE_WIDTH, PAGE_HEIGHT);
+        draw_page(&mut cr, chunk, page * layout.per_page(), layout, options);
+        cr.restore();
+        cr.show_page();
+    }
+
+    surface.finish();
+}
+
+// Draws the sheet as a PNG per page, named `<prefix>-<page>.png`, and gives back their names.
+pub fn generate_png_sheets(entries: &[AlgEntry], output_prefix: &str, layout: &SheetLayout, options: &ImageOptions) -> Vec<String> {
+    let mut filenames = vec![];
+    for (page, chunk) in entries.chunks(layout.per_page()).enumerate() {
+        let (image_width, image_height) = options.style.image_size(PAGE_WIDTH, PAGE_HEIGHT);
+        let mut surface = Surface::create_image(Format::ARGB32, image_width, image_height);
+        let mut cr = Cairo::create(&mut surface);
+        set_up_canvas(&mut cr, &options.style, PAGE_WIDTH, PAGE_HEIGHT);
+        draw_page(&mut cr, chunk, page * layout.per_page(), layout, options);
+
+        let filename = format!("{}-{}.png", output_prefix, page + 1);
+        surface.write_to_png(filename.as_str());
+        filenames.push(filename);
+    }
+    filenames
+}
+
+#[test]
+fn needs_a_case_per_page() {
+    assert_eq!(SheetLayout::new(4, 6).unwrap(), SheetLayout { columns: 4, rows: 6 });
+    assert!(SheetLayout::new(0, 6).is_err());
+    assert!(SheetLayout::new(4, 0).is_err());
+}
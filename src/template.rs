@@ -0,0 +1,85 @@
+use algorithm::Algorithm;
+use cases::subset;
+use lla_error::LLAError;
+
+// The text that goes out with each post, made by filling in placeholders in a template:
+//
+//   {alg}        the alg
+//   {length}     how many moves it is
+//   {case_name}  what the case it solves is called, or nothing if it doesn't have a name
+//   {subset}     which step of the last layer it is, e.g. PLL or ZBLL
+//   {index}      which post this is, counting from 1
+//   {inverse}    the alg backwards, which sets the case up
+//   {link}       the alg on alg.cubing.net
+//
+// `{{` and `}}` are a literal brace.
+
+pub const DEFAULT_TEMPLATE: &'static str = "{alg}";
+
+pub struct PostDetails<'a> {
+    pub alg: &'a Algorithm,
+    pub index: usize,
+    pub case_name: Option<&'a str>,
+}
+
+// alg.cubing.net wants spaces as underscores and primes as dashes.
+pub fn link(alg: &Algorithm) -> String {
+    let encoded = format!("{}", alg).replace(' ', "_").replace('\'', "-");
+    format!("https://alg.cubing.net/?type=alg&alg={}", encoded)
+}
+
+fn value(placeholder: &str, details: &PostDetails) -> Result<String, LLAError> {
+    match placeholder {
+        "alg" => Ok(format!("{}", details.alg)),
+        "length" => Ok(format!("{}", details.alg.length())),
+        "case_name" => Ok(String::from(details.case_name.unwrap_or(""))),
+        "subset" => Ok(String::from(subset(details.alg))),
+        "index" => Ok(format!("{}", details.index)),
+        "inverse" => Ok(format!("{}", details.alg.inverse())),
+        "link" => Ok(link(details.alg)),
+        &_ => Err(LLAError::InvalidOption(format!("No placeholder {{{}}} in post templates", placeholder))),
+    }
+}
+
+pub fn render(template: &str, details: &PostDetails) -> Result<String, LLAError> {
+    let mut result = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            },
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(LLAError::InvalidOption(format!("Placeholder {{{} has no closing }}", placeholder))),
+                    }
+                }
+                result.push_str(value(placeholder.as_str(), details)?.as_str());
+            },
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+#[test]
+fn fills_in_placeholders() {
+    use std::str::FromStr;
+    let alg = Algorithm::from_str("R U R' U R U2 R'").unwrap();
+    let details = PostDetails { alg: &alg, index: 12, case_name: Some("Sune") };
+    assert_eq!(render(DEFAULT_TEMPLATE, &details).unwrap(), "R U R' U R U2 R'");
+    assert_eq!(render("#{index}: {case_name} ({subset}, {length} moves) {{set up with {inverse}}}", &details).unwrap(),
+               "#12: Sune (ZBLL, 7 moves) {set up with R U2 R' U' R U' R'}");
+    assert_eq!(render("{link}", &details).unwrap(), "https://alg.cubing.net/?type=alg&alg=R_U_R-_U_R_U2_R-");
+    assert!(render("{algorithm}", &details).is_err());
+    assert!(render("Today's alg: {alg", &details).is_err());
+}
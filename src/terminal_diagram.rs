@@ -0,0 +1,45 @@
+use cubestate::CubeState;
+use image_style::{sticker_face, ColorScheme};
+
+// The same layout `generate_image` draws: the U face in the middle, with the adjacent sticker of
+// each side face around it. `None` is an empty cell in the corners.
+const LAYOUT: [[Option<usize>; 5]; 5] = [
+    [None,     Some(51), Some(52), Some(53), None    ],
+    [Some(9),  Some(0),  Some(1),  Some(2),  Some(17)],
+    [Some(10), Some(3),  Some(4),  Some(5),  Some(16)],
+    [Some(11), Some(6),  Some(7),  Some(8),  Some(15)],
+    [None,     Some(12), Some(13), Some(14), None    ],
+];
+
+const RESET: &'static str = "\x1b[0m";
+
+fn cell(sticker: Option<usize>, c: &CubeState, colors: &ColorScheme) -> String {
+    match sticker {
+        Some(i) => {
+            let (r, g, b) = colors.face(sticker_face(c.state[i]));
+            let channel = |x: f64| (x * 255.).round() as u8;
+            // 24 bit colour, so that the terminal shows the same colours as the images
+            format!("\x1b[48;2;{};{};{}m  {}", channel(r), channel(g), channel(b), RESET)
+        },
+        None => String::from("  "),
+    }
+}
+
+// Renders the LL case as rows of coloured blocks, with a space between stickers so that
+// neighbouring stickers of the same colour can still be told apart.
+pub fn render(c: CubeState, colors: &ColorScheme) -> String {
+    let mut result = String::new();
+    for row in LAYOUT.iter() {
+        let cells: Vec<String> = row.iter().map(|&s| cell(s, &c, colors)).collect();
+        result.push_str(cells.join(" ").as_str());
+        result.push('\n');
+    }
+    result
+}
+
+#[test]
+fn renders_a_row_per_layout_row() {
+    let rendered = render(CubeState::solved(), &ColorScheme::default());
+    assert_eq!(rendered.lines().count(), 5);
+    assert!(rendered.lines().nth(2).unwrap().contains("\x1b[48;2;255;255;0m  "));
+}
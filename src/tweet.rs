@@ -2,16 +2,19 @@ use std::error::Error;
 use std::io::Read;
 use std::fs::File;
 use std::path::Path;
-use std::collections::HashMap;
 
-use oauth::Token;
-use oauth;
-use rustc_serialize::base64::{ToBase64, Config, CharacterSet, Newline};
-use rustc_serialize::json;
+use rustc_serialize::json::{Json, Object};
+use http::{self, Multipart, Request};
+use oauth::{self, Token};
+use publisher::{Publisher, base_name, media_gone_if, optional_string, read_file, required_string};
 
 const CRED_FNAME: &'static str = "creds";
-const TWITTER_API_UPLOAD_URL: &'static str = "https://upload.twitter.com/1.1/media/upload.json";
-const TWITTER_API_TWEET_URL: &'static str = "https://api.twitter.com/1.1/statuses/update.json";
+const TWITTER_API_URL: &'static str = "https://api.twitter.com";
+const TWITTER_API_UPLOAD_PATH: &'static str = "/2/media/upload";
+const TWITTER_API_TWEET_PATH: &'static str = "/2/tweets";
+const TWITTER_API_METADATA_PATH: &'static str = "/2/media/metadata";
+// Twitter won't take alt text any longer than this
+const MAX_ALT_TEXT_CHARS: usize = 1000;
 
 #[derive(Debug)]
 struct Creds {
@@ -26,8 +29,8 @@ impl Creds {
         lines.next().map(|l| String::from(l)).ok_or(From::from("Bad credentials file"))
     }
 
-    fn load() -> Result<Self, Box<Error>> {
-        let path = Path::new(CRED_FNAME);
+    fn load(filename: &str) -> Result<Self, Box<Error>> {
+        let path = Path::new(filename);
         let mut file = File::open(&path)?;
         let mut s = String::new();
         file.read_to_string(&mut s)?;
@@ -43,51 +46,185 @@ impl Creds {
             access_secret: access_secret,
         })
     }
+
+    fn from_config(section: &Object) -> Result<Self, Box<Error>> {
+        Ok(Creds {
+            consumer_key: required_string(section, "twitter", "consumer_key")?,
+            consumer_secret: required_string(section, "twitter", "consumer_secret")?,
+            access_key: required_string(section, "twitter", "access_token")?,
+            access_secret: required_string(section, "twitter", "access_token_secret")?,
+        })
+    }
 }
 
-type MediaId = u64;
+pub struct Twitter {
+    consumer: Token,
+    access: Token,
+    api_url: String,
+}
 
-#[derive(RustcDecodable)]
-struct TwitterUploadResponse {
-    media_id: MediaId
+impl Twitter {
+    // The credentials are "consumer_key", "consumer_secret", "access_token" and
+    // "access_token_secret", or can be read from "creds", a file with those four on their own
+    // lines. The old `creds` file in the working directory is still read if there's neither.
+    // "api_url" points it somewhere other than the real Twitter.
+    pub fn from_config(section: &Object) -> Result<Self, Box<Error>> {
+        let api_url = optional_string(section, "api_url")?.unwrap_or(TWITTER_API_URL);
+        let creds = match optional_string(section, "creds")? {
+            Some(filename) => Creds::load(filename)?,
+            None if !section.contains_key("consumer_key") && Path::new(CRED_FNAME).exists() => Creds::load(CRED_FNAME)?,
+            None => Creds::from_config(section)?,
+        };
+        Ok(Twitter {
+            consumer: Token::new(creds.consumer_key, creds.consumer_secret),
+            access: Token::new(creds.access_key, creds.access_secret),
+            api_url: String::from(api_url.trim_right_matches('/')),
+        })
+    }
+
+    // Signed afresh each time it's sent, as Twitter turns down a nonce it's already seen.
+    fn signed(&self, method: &'static str, path: &str) -> Request {
+        let (consumer, access) = (self.consumer.clone(), self.access.clone());
+        Request::new(method, format!("{}{}", self.api_url, path).as_str()).signed_by(move |req| {
+            (String::from("Authorization"), oauth::authorization_header(req.method, req.url.as_str(), &[], &consumer, &access))
+        })
+    }
 }
 
-fn upload_image(consumer: &Token, access: &Token, filename: &str) -> Result<MediaId, Box<Error>> {
-    let mut file = File::open(filename)?;
-    let mut buf: Vec<u8> = vec![];
-    file.read_to_end(&mut buf)?;
-
-    let base64_encoded_image: String = buf.to_base64(Config {
-        char_set: CharacterSet::Standard,
-        newline: Newline::LF,
-        pad: true,
-        line_length: None
-    });
-
-    let mut param = HashMap::new();
-    param.insert("media_data".into(), base64_encoded_image.into());
-    let result = oauth::post(TWITTER_API_UPLOAD_URL, consumer, Some(access), Some(&param))?;
-    let response: TwitterUploadResponse = json::decode(String::from_utf8(result)?.as_str())?;
-    Ok(response.media_id)
+impl Publisher for Twitter {
+    fn name(&self) -> &str {
+        "twitter"
+    }
+
+    fn upload_media(&self, filename: &str) -> Result<String, Box<Error>> {
+        let category = if filename.ends_with(".gif") { "tweet_gif" } else { "tweet_image" };
+        let form = Multipart::new()
+            .text("media_category", category)
+            .file("media", base_name(filename).as_str(), &read_file(filename)?);
+        let response = http::send(&self.signed("POST", TWITTER_API_UPLOAD_PATH).multipart(form).repeatable())?;
+        response.field(&["data", "id"])
+    }
+
+    fn set_alt_text(&self, media_id: &str, alt_text: &str) -> Result<(), Box<Error>> {
+        let mut text = Object::new();
+        text.insert(String::from("text"), Json::String(alt_text.chars().take(MAX_ALT_TEXT_CHARS).collect()));
+        let mut metadata = Object::new();
+        metadata.insert(String::from("alt_text"), Json::Object(text));
+        let mut body = Object::new();
+        body.insert(String::from("id"), Json::String(String::from(media_id)));
+        body.insert(String::from("metadata"), Json::Object(metadata));
+
+        http::send(&self.signed("POST", TWITTER_API_METADATA_PATH).json(&Json::Object(body)).repeatable())?;
+        Ok(())
+    }
+
+    fn post_status(&self, status: &str, media_id: &str) -> Result<String, Box<Error>> {
+        let mut media = Object::new();
+        media.insert(String::from("media_ids"), Json::Array(vec![Json::String(String::from(media_id))]));
+        let mut body = Object::new();
+        body.insert(String::from("text"), Json::String(String::from(status)));
+        body.insert(String::from("media"), Json::Object(media));
+
+        // uploads only last a day or so unposted, after which the ids are turned down
+        let request = self.signed("POST", TWITTER_API_TWEET_PATH).json(&Json::Object(body));
+        let response = http::send(&request).map_err(|err| media_gone_if(err, 400, "media IDs are invalid"))?;
+        response.field(&["data", "id"])
+    }
+}
+
+#[test]
+fn posts_signed_requests_to_twitter_api() {
+    use mock_server::{MockServer, has_valid_oauth, temp_file};
+
+    let server = MockServer::start(vec![
+        (200, r#"{"data": {"id": "1234", "media_key": "3_1234"}}"#),
+        (200, r#"{"data": {"id": "1234", "associated_metadata": {}}}"#),
+        (201, r#"{"data": {"id": "5678", "text": "R U R' U R U2 R'"}}"#),
+    ]);
+    let twitter = test_twitter(server.url.as_str());
+    let image = temp_file("twitter.png", b"PNG");
+    let media_id = twitter.upload_media(image.as_str()).unwrap();
+    twitter.set_alt_text(media_id.as_str(), "A last layer case").unwrap();
+    assert_eq!(twitter.post_status("R U R' U R U2 R'", media_id.as_str()).unwrap(), "5678");
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, TWITTER_API_UPLOAD_PATH);
+    assert!(has_valid_oauth(&requests[0], format!("{}{}", twitter.api_url, TWITTER_API_UPLOAD_PATH).as_str(), &twitter.consumer, &twitter.access));
+    assert!(requests[0].body_string().contains("name=\"media_category\"\r\n\r\ntweet_image\r\n"));
+
+    assert_eq!(requests[1].path, TWITTER_API_METADATA_PATH);
+    assert!(has_valid_oauth(&requests[1], format!("{}{}", twitter.api_url, TWITTER_API_METADATA_PATH).as_str(), &twitter.consumer, &twitter.access));
+    assert_eq!(Json::from_str(requests[1].body_string().as_str()).unwrap(),
+               Json::from_str(r#"{"id": "1234", "metadata": {"alt_text": {"text": "A last layer case"}}}"#).unwrap());
+
+    assert_eq!(requests[2].path, TWITTER_API_TWEET_PATH);
+    assert!(has_valid_oauth(&requests[2], format!("{}{}", twitter.api_url, TWITTER_API_TWEET_PATH).as_str(), &twitter.consumer, &twitter.access));
+    assert!(!has_valid_oauth(&requests[2], format!("{}{}", twitter.api_url, TWITTER_API_UPLOAD_PATH).as_str(), &twitter.consumer, &twitter.access));
+    assert_eq!(Json::from_str(requests[2].body_string().as_str()).unwrap(),
+               Json::from_str(r#"{"text": "R U R' U R U2 R'", "media": {"media_ids": ["1234"]}}"#).unwrap());
+
+    ::std::fs::remove_file(image).unwrap();
+}
+
+#[cfg(test)]
+fn test_twitter(api_url: &str) -> Twitter {
+    Twitter {
+        consumer: Token::new(String::from("consumer key"), String::from("consumer secret")),
+        access: Token::new(String::from("access key"), String::from("access secret")),
+        api_url: String::from(api_url),
+    }
 }
 
-pub fn post_tweet(consumer: &Token, access: &Token, status: &str, filename: &str) -> Result<(), Box<Error>> {
-    // Posting a tweet with an image takes two api calls, one to upload the image (which gives us
-    // back an identifier for the image) and one to post the tweet (which includes the identifier)
-    let media_id = upload_image(consumer, access, filename)?;
-    let mut parameters = HashMap::new();
-    parameters.insert("status".into(), status.into());
-    parameters.insert("media_ids".into(), format!("{}", media_id).into());
-    oauth::post(TWITTER_API_TWEET_URL, consumer, Some(access), Some(&parameters))?;
-    Ok(())
+#[test]
+fn keeps_the_media_if_the_post_might_have_gone_through() {
+    use journal::Journal;
+    use mock_server::MockServer;
+    use publisher::publish_everywhere;
+
+    let server = MockServer::start(vec![
+        (200, r#"{"data": {"id": "1234", "associated_metadata": {}}}"#),
+        (503, r#"{"title": "Service Unavailable"}"#),
+    ]);
+    let publishers: Vec<Box<Publisher>> = vec![Box::new(test_twitter(server.url.as_str()))];
+    let mut journal = Journal::in_memory();
+    journal.start("R U R' U R U2 R'", "R U R' U R U2 R'").unwrap();
+    journal.record_media("twitter", "1234").unwrap();
+    assert!(publish_everywhere(&publishers, &mut journal, "unused.png", "").is_err());
+    assert_eq!(journal.progress("twitter").unwrap().media_id, Some(String::from("1234")));
+
+    // no second go at the post, and nothing uploaded again
+    let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+    assert_eq!(paths, vec![TWITTER_API_METADATA_PATH, TWITTER_API_TWEET_PATH]);
 }
 
-pub fn tweet(alg: &str, image_filename: &str) -> Result<(), Box<Error>> {
-    let creds = Creds::load()?;
+#[test]
+fn turns_down_expired_media() {
+    use lla_error::LLAError;
+    use mock_server::MockServer;
+
+    let server = MockServer::start(vec![
+        (400, r#"{"errors": [{"message": "Your media IDs are invalid."}], "title": "Invalid Request"}"#),
+        (400, r#"{"errors": [{"message": "Text is too long."}], "title": "Invalid Request"}"#),
+    ]);
+    let twitter = test_twitter(server.url.as_str());
+    let gone = twitter.post_status("R U R' U R U2 R'", "1234").err().unwrap();
+    assert!(match gone.downcast_ref::<LLAError>() { Some(&LLAError::MediaGone(_)) => true, _ => false });
+    let other = twitter.post_status("R U R' U R U2 R'", "1234").err().unwrap();
+    assert!(other.downcast_ref::<LLAError>().is_none());
+    server.requests();
+}
 
-    let consumer = Token::new(creds.consumer_key, creds.consumer_secret);
-    let access = Token::new(creds.access_key, creds.access_secret);
-    post_tweet(&consumer, &access, alg, image_filename)?;
+#[test]
+fn reads_credentials_from_config() {
+    let config = Json::from_str(r#"{
+        "consumer_key": "ck", "consumer_secret": "cs", "access_token": "at", "access_token_secret": "ats"
+    }"#).unwrap();
+    let twitter = Twitter::from_config(config.as_object().unwrap()).unwrap();
+    assert_eq!((twitter.consumer.key.as_str(), twitter.consumer.secret.as_str()), ("ck", "cs"));
+    assert_eq!((twitter.access.key.as_str(), twitter.access.secret.as_str()), ("at", "ats"));
+    assert_eq!(twitter.api_url, TWITTER_API_URL);
 
-    Ok(())
+    let config = Json::from_str(r#"{"consumer_key": "ck", "consumer_secret": "cs", "access_token": "at"}"#).unwrap();
+    let err = Twitter::from_config(config.as_object().unwrap()).err().unwrap();
+    assert!(format!("{}", err).contains("'access_token_secret'"));
 }
@@ -0,0 +1,65 @@
+use std::cell::RefCell;
+use std::error::Error;
+use rustc_serialize::base64::{ToBase64, STANDARD};
+use rustc_serialize::json::{Json, Object};
+use http::{self, Request};
+use publisher::{Publisher, base_name, read_file, required_string};
+
+// Posts the status and image as one JSON request to a URL of your choosing, for anything that
+// isn't built in:
+//
+//   {
+//     "status": "R U R' U R U2 R'",
+//     "media": {
+//       "filename": "output_file.png",
+//       "content_type": "image/png",
+//       "data": "<base64>",
+//       "alt_text": "A last layer case, seen from above. ..."
+//     }
+//   }
+//
+// Whatever answers can give back an "id" for the post, otherwise it's counted as "ok".
+pub struct Webhook {
+    url: String,
+    // held on to until the status goes out, as that's when the media does too
+    alt_text: RefCell<Option<String>>,
+}
+
+impl Webhook {
+    pub fn from_config(section: &Object) -> Result<Self, Box<Error>> {
+        Ok(Webhook { url: required_string(section, "webhook", "url")?, alt_text: RefCell::new(None) })
+    }
+}
+
+impl Publisher for Webhook {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    // There's nowhere separate to upload to, so the "id" is just the file, which goes out with
+    // the status.
+    fn upload_media(&self, filename: &str) -> Result<String, Box<Error>> {
+        Ok(String::from(filename))
+    }
+
+    fn set_alt_text(&self, _media_id: &str, alt_text: &str) -> Result<(), Box<Error>> {
+        *self.alt_text.borrow_mut() = Some(String::from(alt_text));
+        Ok(())
+    }
+
+    fn post_status(&self, status: &str, media_id: &str) -> Result<String, Box<Error>> {
+        let mut media = Object::new();
+        media.insert(String::from("filename"), Json::String(base_name(media_id)));
+        media.insert(String::from("content_type"), Json::String(String::from(http::content_type(media_id))));
+        media.insert(String::from("data"), Json::String(read_file(media_id)?.to_base64(STANDARD)));
+        if let Some(ref alt_text) = *self.alt_text.borrow() {
+            media.insert(String::from("alt_text"), Json::String(alt_text.clone()));
+        }
+        let mut body = Object::new();
+        body.insert(String::from("status"), Json::String(String::from(status)));
+        body.insert(String::from("media"), Json::Object(media));
+
+        let response = http::send(&Request::new("POST", self.url.as_str()).json(&Json::Object(body)))?;
+        Ok(response.field(&["id"]).unwrap_or(String::from("ok")))
+    }
+}